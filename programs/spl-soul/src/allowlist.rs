@@ -0,0 +1,110 @@
+//! Merkle allowlist for the presale. Each leaf commits to a wallet and the
+//! amount of SOUL base units it may buy during the allowlist phase. Pairs are
+//! hashed in sorted order, so proofs carry no left/right flags.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::keccak::hashv;
+
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+
+pub fn leaf(wallet: &Pubkey, allocation: u64) -> [u8; 32] {
+    hashv(&[LEAF_PREFIX, wallet.as_ref(), &allocation.to_le_bytes()]).to_bytes()
+}
+
+fn hash_pair(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+    hashv(&[NODE_PREFIX, first, second]).to_bytes()
+}
+
+pub fn verify(root: &[u8; 32], leaf: [u8; 32], proof: &[[u8; 32]]) -> bool {
+    let computed = proof.iter().fold(leaf, |node, sibling| hash_pair(&node, sibling));
+    computed == *root
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum AllowlistCsvError {
+    Empty,
+    InvalidLine(usize), // 1-based line number
+    DuplicateWallet(Pubkey),
+}
+
+/// Builds the allowlist tree and proofs off-chain
+#[cfg(not(target_os = "solana"))]
+pub struct AllowlistTree {
+    entries: Vec<(Pubkey, u64)>,
+    layers: Vec<Vec<[u8; 32]>>, // Leaves first, root last
+}
+
+#[cfg(not(target_os = "solana"))]
+impl AllowlistTree {
+    pub fn new(entries: Vec<(Pubkey, u64)>) -> std::result::Result<Self, AllowlistCsvError> {
+        if entries.is_empty() {
+            return Err(AllowlistCsvError::Empty);
+        }
+        for (index, (wallet, _)) in entries.iter().enumerate() {
+            if entries[..index].iter().any(|(other, _)| other == wallet) {
+                return Err(AllowlistCsvError::DuplicateWallet(*wallet));
+            }
+        }
+
+        let mut layers = vec![entries
+            .iter()
+            .map(|(wallet, allocation)| leaf(wallet, *allocation))
+            .collect::<Vec<_>>()];
+        while layers[layers.len() - 1].len() > 1 {
+            // An odd node is carried up unchanged
+            let next = layers[layers.len() - 1]
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => hash_pair(a, b),
+                    [a] => *a,
+                    _ => unreachable!(),
+                })
+                .collect();
+            layers.push(next);
+        }
+
+        Ok(Self { entries, layers })
+    }
+
+    /// Parses `wallet,allocation` lines. Blank lines and a header line that
+    /// does not start with a valid wallet are skipped.
+    pub fn from_csv(csv: &str) -> std::result::Result<Self, AllowlistCsvError> {
+        let mut entries = Vec::new();
+        for (index, line) in csv.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let mut fields = line.split(',').map(str::trim);
+            let wallet = fields.next().unwrap_or_default().parse::<Pubkey>();
+            let allocation = fields.next().map(str::parse::<u64>);
+            match (wallet, allocation, fields.next()) {
+                (Ok(wallet), Some(Ok(allocation)), None) => entries.push((wallet, allocation)),
+                (Err(_), _, _) if index == 0 => continue,
+                _ => return Err(AllowlistCsvError::InvalidLine(index + 1)),
+            }
+        }
+        Self::new(entries)
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.layers[self.layers.len() - 1][0]
+    }
+
+    /// Allocation and proof of `wallet`, if it is allowlisted
+    pub fn proof(&self, wallet: &Pubkey) -> Option<(u64, Vec<[u8; 32]>)> {
+        let position = self.entries.iter().position(|(other, _)| other == wallet)?;
+        let mut index = position;
+        let mut proof = Vec::new();
+        for layer in &self.layers[..self.layers.len() - 1] {
+            if let Some(sibling) = layer.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+        Some((self.entries[position].1, proof))
+    }
+}
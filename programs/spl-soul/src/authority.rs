@@ -0,0 +1,40 @@
+//! Two-step authority handover shared by `TokenState` and `PresaleState`.
+//! The current authority proposes a key, which must sign to accept; a
+//! default key means nothing is pending.
+
+use anchor_lang::prelude::*;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum AuthorityError {
+    NoPendingAuthority,
+    NotPendingAuthority,
+}
+
+/// Starts a handover, replacing any pending proposal
+pub fn propose(pending_authority: &mut Pubkey, new_authority: Pubkey) {
+    *pending_authority = new_authority;
+}
+
+/// Drops the pending handover and returns the key that was proposed
+pub fn cancel(pending_authority: &mut Pubkey) -> std::result::Result<Pubkey, AuthorityError> {
+    if *pending_authority == Pubkey::default() {
+        return Err(AuthorityError::NoPendingAuthority);
+    }
+    Ok(std::mem::take(pending_authority))
+}
+
+/// Completes the handover when signed by the proposed key, returns the previous authority
+pub fn accept(
+    authority: &mut Pubkey,
+    pending_authority: &mut Pubkey,
+    signer: &Pubkey,
+) -> std::result::Result<Pubkey, AuthorityError> {
+    if *pending_authority == Pubkey::default() {
+        return Err(AuthorityError::NoPendingAuthority);
+    }
+    if pending_authority != signer {
+        return Err(AuthorityError::NotPendingAuthority);
+    }
+    let new_authority = std::mem::take(pending_authority);
+    Ok(std::mem::replace(authority, new_authority))
+}
@@ -0,0 +1,116 @@
+use anchor_lang::prelude::*;
+
+use crate::vesting::{ReleaseMode, VestingTerms};
+
+pub const TEAM_ALLOCATION: u8 = 0;
+pub const DEVELOPMENT_ALLOCATION: u8 = 1;
+pub const COMMUNITY_ALLOCATION: u8 = 2;
+
+pub const MAX_VESTING_BUCKETS: usize = 8;
+pub const MAX_DECIMALS: u8 = 9;
+pub const TOTAL_BPS: u32 = 10_000;
+
+const DAY: i64 = 24 * 60 * 60;
+
+/// A share of the supply released through a `VestingSchedule`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct VestingBucket {
+    pub allocation: u8,
+    pub supply_bps: u16,
+    pub period_bps: u16, // Of the total supply released each period, zero splits the amount after TGE evenly
+    pub terms: VestingTerms,
+}
+
+impl VestingBucket {
+    pub const LEN: usize = 1 + 2 + 2 + VestingTerms::LEN;
+}
+
+/// Tokenomics passed to `initialize`, shares are in basis points of the total supply
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct AllocationConfig {
+    pub decimals: u8,
+    pub dex_liquidity_bps: u16,
+    pub cex_marketing_bps: u16, // Fully available at TGE
+    pub presale_bps: u16,
+    pub vesting: Vec<VestingBucket>,
+}
+
+impl AllocationConfig {
+    pub const LEN: usize = 1 + 2 + 2 + 2 + 4 + VestingBucket::LEN * MAX_VESTING_BUCKETS;
+
+    /// The SOUL split: team 10%, DEX 5%, CEX/marketing 10%, development 30%,
+    /// community 15% and pre-sale 30% with 6 decimals
+    pub fn soul() -> Self {
+        Self {
+            decimals: 6,
+            dex_liquidity_bps: 500,
+            cex_marketing_bps: 1_000,
+            presale_bps: 3_000,
+            vesting: vec![
+                // 25% every 6 months, nothing at TGE
+                VestingBucket {
+                    allocation: TEAM_ALLOCATION,
+                    supply_bps: 1_000,
+                    period_bps: 0,
+                    terms: VestingTerms {
+                        tge_percentage: 0,
+                        cliff_seconds: 180 * DAY,
+                        period_seconds: 180 * DAY,
+                        period_count: 4,
+                        release: ReleaseMode::Periodic,
+                    },
+                },
+                // One sixth at TGE, then one sixth every 180 days
+                VestingBucket {
+                    allocation: DEVELOPMENT_ALLOCATION,
+                    supply_bps: 3_000,
+                    period_bps: 0,
+                    terms: VestingTerms {
+                        tge_percentage: 0,
+                        cliff_seconds: 0,
+                        period_seconds: 180 * DAY,
+                        period_count: 6,
+                        release: ReleaseMode::Periodic,
+                    },
+                },
+                // 30% at TGE, then 2% of the supply (200M SOUL) every 60 days for 4 periods
+                VestingBucket {
+                    allocation: COMMUNITY_ALLOCATION,
+                    supply_bps: 1_500,
+                    period_bps: 200,
+                    terms: VestingTerms {
+                        tge_percentage: 30,
+                        cliff_seconds: 60 * DAY,
+                        period_seconds: 60 * DAY,
+                        period_count: 4,
+                        release: ReleaseMode::Periodic,
+                    },
+                },
+            ],
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        if self.decimals > MAX_DECIMALS || self.vesting.len() > MAX_VESTING_BUCKETS {
+            return false;
+        }
+
+        let mut total_bps =
+            self.dex_liquidity_bps as u32 + self.cex_marketing_bps as u32 + self.presale_bps as u32;
+        for (index, bucket) in self.vesting.iter().enumerate() {
+            let duplicate = self.vesting[..index]
+                .iter()
+                .any(|other| other.allocation == bucket.allocation);
+            if duplicate || bucket.period_bps > bucket.supply_bps || !bucket.terms.is_valid() {
+                return false;
+            }
+            total_bps += bucket.supply_bps as u32;
+        }
+
+        total_bps == TOTAL_BPS
+    }
+
+    pub fn vesting_bucket(&self, allocation: u8) -> Option<&VestingBucket> {
+        self.vesting.iter().find(|bucket| bucket.allocation == allocation)
+    }
+}
@@ -1,5 +1,159 @@
 use anchor_lang::prelude::*;
 
+use crate::presale::{PaymentCurrency, SplitShare};
+use crate::roles::Role;
+
+#[event]
+pub struct TokensUnlocked {
+    #[index]
+    pub allocation: u8,
+    pub receiver: Pubkey,
+    pub amount: u64,
+    pub total_unlocked: u64,
+    pub time: i64,
+}
+
+#[event]
+pub struct BeneficiaryChangeProposed {
+    #[index]
+    pub allocation: u8,
+    pub current_beneficiary: Pubkey,
+    pub pending_beneficiary: Pubkey,
+    pub eta: i64,
+}
+
+#[event]
+pub struct BeneficiaryChanged {
+    #[index]
+    pub allocation: u8,
+    pub previous_beneficiary: Pubkey,
+    pub new_beneficiary: Pubkey,
+    pub time: i64,
+}
+
+#[event]
+pub struct CexMarketingReleased {
+    #[index]
+    pub receiver: Pubkey,
+    pub amount: u64,
+    pub total_drawn: u64,
+    pub remaining: u64,
+    pub time: i64,
+}
+
+#[event]
+pub struct MintAuthorityMigrated {
+    pub mint: Pubkey,
+    pub previous_authority: Pubkey,
+    pub new_authority: Pubkey,
+}
+
+#[event]
+pub struct MultisigOwnersSet {
+    pub owners: Vec<Pubkey>,
+    pub threshold: u8,
+    pub owner_set_seqno: u32,
+}
+
+#[event]
+pub struct ProposalCreated {
+    #[index]
+    pub index: u64,
+    pub proposer: Pubkey,
+    pub program_id: Pubkey,
+}
+
+#[event]
+pub struct ProposalApproved {
+    #[index]
+    pub index: u64,
+    pub owner: Pubkey,
+    pub approvals: u8,
+    pub threshold: u8,
+}
+
+#[event]
+pub struct ProposalExecuted {
+    #[index]
+    pub index: u64,
+    pub executor: Pubkey,
+    pub time: i64,
+}
+
+#[event]
+pub struct TimelockConfigSet {
+    pub admin: Pubkey,
+    pub min_delay: i64,
+}
+
+#[event]
+pub struct ActionQueued {
+    #[index]
+    pub index: u64,
+    pub program_id: Pubkey,
+    pub data: Vec<u8>, // Instruction data, so the change can be decoded ahead of the eta
+    pub eta: i64,
+}
+
+#[event]
+pub struct ActionExecuted {
+    #[index]
+    pub index: u64,
+    pub time: i64,
+}
+
+#[event]
+pub struct ActionCancelled {
+    #[index]
+    pub index: u64,
+    pub time: i64,
+}
+
+/// Emitted by both programs, `registry` is the roles account of the program
+#[event]
+pub struct RoleGranted {
+    #[index]
+    pub registry: Pubkey,
+    pub role: Role,
+    pub member: Pubkey,
+    pub admin: Pubkey,
+}
+
+#[event]
+pub struct RoleRevoked {
+    #[index]
+    pub registry: Pubkey,
+    pub role: Role,
+    pub member: Pubkey,
+    pub admin: Pubkey,
+}
+
+/// Emitted by both programs, `state` is the `TokenState` or `PresaleState` account
+#[event]
+pub struct AuthorityTransferProposed {
+    #[index]
+    pub state: Pubkey,
+    pub current_authority: Pubkey,
+    pub pending_authority: Pubkey,
+}
+
+#[event]
+pub struct AuthorityTransferCancelled {
+    #[index]
+    pub state: Pubkey,
+    pub authority: Pubkey,
+    pub cancelled_authority: Pubkey,
+}
+
+#[event]
+pub struct AuthorityTransferred {
+    #[index]
+    pub state: Pubkey,
+    pub previous_authority: Pubkey,
+    pub new_authority: Pubkey,
+    pub time: i64,
+}
+
 #[event]
 pub struct TokensPurchased {
     #[index]
@@ -9,6 +163,21 @@ pub struct TokensPurchased {
     pub stage: u8,
 }
 
+#[event]
+pub struct TokensClaimed {
+    #[index]
+    pub buyer: Pubkey,
+    pub amount: u64,
+    pub total_claimed: u64,
+    pub time: i64,
+}
+
+#[event]
+pub struct AllowlistUpdated {
+    pub root: [u8; 32],
+    pub end_time: i64,
+}
+
 #[event]
 pub struct StageOpened {
     #[index]
@@ -25,6 +194,7 @@ pub struct StageClosed {
     pub stage: u8,
     pub time_closed: i64,
     pub tokens_sold: u64,
+    pub bonus_tokens: u64, // Referral bonuses, sold plus bonuses plus left is the stage allocation
     pub tokens_left: u64,
 }
 
@@ -45,10 +215,65 @@ pub struct TokensBurned {
     pub time: i64,
 }
 
+#[event]
+pub struct ReferralCredited {
+    #[index]
+    pub referrer: Pubkey,
+    pub buyer: Pubkey,
+    pub bonus_tokens: u64,
+    pub commission: u64, // In base units of the payment stablecoin
+    pub commission_usd: u64,
+}
+
+#[event]
+pub struct ReferralRewardsClaimed {
+    #[index]
+    pub referrer: Pubkey,
+    pub usdt_amount: u64,
+    pub usdc_amount: u64,
+    pub bonus_tokens: u64,
+    pub time: i64,
+}
+
+#[event]
+pub struct RefundClaimed {
+    #[index]
+    pub buyer: Pubkey,
+    pub usdt_amount: u64,
+    pub usdc_amount: u64,
+    pub lamports: u64,
+    pub time: i64,
+}
+
 #[event]
 pub struct FundsWithdrawn {
     #[index]
     pub receiver: Pubkey,
+    pub currency: PaymentCurrency,
     pub amount: u64,
+    pub remaining_allowance: u64, // Left in the current withdrawal period
+    pub time: i64,
+}
+
+#[event]
+pub struct TreasurySplitUpdated {
+    pub shares: Vec<SplitShare>,
+}
+
+#[event]
+pub struct SplitDistributed {
+    #[index]
+    pub recipient: Pubkey,
+    pub usdt_amount: u64,
+    pub usdc_amount: u64,
+    pub lamports: u64,
     pub time: i64,
+}
+
+#[event]
+pub struct TreasuryPolicyUpdated {
+    pub destinations: Vec<Pubkey>,
+    pub usdt_period_limit: u64,
+    pub usdc_period_limit: u64,
+    pub sol_period_limit: u64,
 } 
\ No newline at end of file
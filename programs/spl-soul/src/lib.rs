@@ -1,149 +1,618 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Mint, Token, TokenAccount};
+use anchor_lang::solana_program::program::invoke_signed;
+use anchor_spl::token::{self, spl_token::instruction::AuthorityType, Mint, Token, TokenAccount};
 
 pub mod presale;
 pub mod events;
+pub mod vesting;
+pub mod supply;
+pub mod math;
+pub mod config;
+pub mod allowlist;
+pub mod multisig;
+pub mod timelock;
+pub mod roles;
+pub mod authority;
 
 use presale::*;
 use events::*;
+use supply::*;
+use math::*;
+use vesting::VestingTerms;
+use config::AllocationConfig;
+use multisig::*;
+use timelock::*;
+use roles::{Role, RoleError, RoleRegistry};
+use authority::AuthorityError;
 
 // Replace this key with your real Base58 key generated via `anchor keys gen`
 declare_id!("G1RZSqt72nyisqmEaocAMV42fKwepARaAo17JtL1rGoW");
 
+/// Delay before a proposed vesting beneficiary can replace the current one
+pub const BENEFICIARY_CHANGE_DELAY: i64 = 7 * 24 * 60 * 60;
+
 #[program]
 mod soul_token {
     use super::*;
 
-    pub fn initialize(ctx: Context<Initialize>, total_supply: u64) -> Result<()> {
+    pub fn initialize(ctx: Context<Initialize>, total_supply: u64, config: AllocationConfig) -> Result<()> {
+        require!(config.is_valid(), TokenError::InvalidAllocation);
+
+        let total_supply =
+            to_base_units(total_supply, config.decimals as u32).ok_or(TokenError::MathOverflow)?;
+        let share = |bps: u16| bps_of(total_supply, bps as u64).ok_or(TokenError::MathOverflow);
+
+        // Vesting buckets are minted through VestingSchedule accounts created by add_vesting_schedule
+        let vesting_shares = config
+            .vesting
+            .iter()
+            .map(|bucket| share(bucket.supply_bps))
+            .collect::<std::result::Result<Vec<u64>, _>>()?;
+
         let state = &mut ctx.accounts.state;
-        state.total_supply = total_supply * 10u64.pow(6); // Account for 6 decimals
+        state.total_supply = total_supply; // In base units
         state.authority = ctx.accounts.authority.key();
+        state.pending_authority = Pubkey::default();
+        state.mint = ctx.accounts.mint.key();
 
-        state.team_supply = state.total_supply * 10 / 100; // 10%
-        state.dex_liquidity_supply = state.total_supply * 5 / 100; // 5%
-        state.cex_marketing_supply = state.total_supply * 10 / 100; // 10%
+        state.dex_liquidity_supply = share(config.dex_liquidity_bps)?;
+        state.cex_marketing_supply = share(config.cex_marketing_bps)?;
         state.cex_marketing_unlocked = state.cex_marketing_supply; // Available at TGE
-        state.development_supply = state.total_supply * 30 / 100; // 30%
-        state.development_unlocked = state.development_supply / 6; // 5% at TGE
-        state.community_supply = state.total_supply * 15 / 100; // 15% (1.5B SOUL)
-        state.community_unlocked = state.community_supply * 30 / 100; // 30% at TGE
+        state.cex_marketing_drawn = 0;
+        state.vesting_supply = checked_sum(&vesting_shares).ok_or(TokenError::MathOverflow)?;
 
         state.tge_time = Clock::get()?.unix_timestamp;
 
-        // The remaining 30% (Pre Sale) will be managed by a separate contract
+        // The Pre Sale share will be managed by a separate contract
+        let ledger = &mut ctx.accounts.ledger;
+        ledger.total_cap = state.total_supply;
+        ledger.total_minted = 0;
+        ledger.vesting = AllocationSupply { cap: state.vesting_supply, minted: 0 };
+        ledger.dex_liquidity = AllocationSupply { cap: state.dex_liquidity_supply, minted: 0 };
+        ledger.cex_marketing = AllocationSupply { cap: state.cex_marketing_supply, minted: 0 };
+        ledger.presale = AllocationSupply { cap: share(config.presale_bps)?, minted: 0 };
+
+        state.config = config;
+        Ok(())
+    }
+
+    pub fn add_vesting_schedule(ctx: Context<AddVestingSchedule>, allocation: u8) -> Result<()> {
+        let state = &ctx.accounts.state;
+        let bucket = state
+            .config
+            .vesting_bucket(allocation)
+            .ok_or(TokenError::UnknownAllocation)?;
+
+        let schedule = &mut ctx.accounts.schedule;
+        schedule.allocation = allocation;
+        schedule.total_amount =
+            bps_of(state.total_supply, bucket.supply_bps as u64).ok_or(TokenError::MathOverflow)?;
+        schedule.amount_per_period =
+            bps_of(state.total_supply, bucket.period_bps as u64).ok_or(TokenError::MathOverflow)?;
+        schedule.unlocked_amount = 0;
+        schedule.terms = bucket.terms;
+        schedule.start_time = state.tge_time;
+        schedule.beneficiary = ctx.accounts.beneficiary.key();
+        schedule.pending_beneficiary = Pubkey::default();
+        schedule.beneficiary_change_eta = 0;
+        Ok(())
+    }
+
+    pub fn propose_beneficiary(ctx: Context<ProposeBeneficiary>, allocation: u8) -> Result<()> {
+        let schedule = &mut ctx.accounts.schedule;
+        let current_time = Clock::get()?.unix_timestamp;
+
+        schedule.pending_beneficiary = ctx.accounts.new_beneficiary.key();
+        schedule.beneficiary_change_eta = current_time
+            .checked_add(BENEFICIARY_CHANGE_DELAY)
+            .ok_or(TokenError::MathOverflow)?;
+
+        emit!(BeneficiaryChangeProposed {
+            allocation,
+            current_beneficiary: schedule.beneficiary,
+            pending_beneficiary: schedule.pending_beneficiary,
+            eta: schedule.beneficiary_change_eta,
+        });
+        Ok(())
+    }
+
+    pub fn apply_beneficiary_change(ctx: Context<ApplyBeneficiaryChange>, allocation: u8) -> Result<()> {
+        let schedule = &mut ctx.accounts.schedule;
+        let current_time = Clock::get()?.unix_timestamp;
+
+        require!(schedule.beneficiary_change_eta != 0, TokenError::NoPendingBeneficiary);
+        require!(current_time >= schedule.beneficiary_change_eta, TokenError::TimelockNotExpired);
+
+        let previous_beneficiary = schedule.beneficiary;
+        schedule.beneficiary = schedule.pending_beneficiary;
+        schedule.pending_beneficiary = Pubkey::default();
+        schedule.beneficiary_change_eta = 0;
+
+        emit!(BeneficiaryChanged {
+            allocation,
+            previous_beneficiary,
+            new_beneficiary: schedule.beneficiary,
+            time: current_time,
+        });
+        Ok(())
+    }
+
+    /// First step of an authority handover, the new authority has to accept it
+    pub fn propose_authority(ctx: Context<TransferAuthority>, new_authority: Pubkey) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        state.propose_authority(new_authority);
+
+        emit!(AuthorityTransferProposed {
+            state: state.key(),
+            current_authority: state.authority,
+            pending_authority: new_authority,
+        });
         Ok(())
     }
 
-    pub fn unlock_team_tokens(ctx: Context<UnlockTokens>) -> Result<()> {
+    pub fn cancel_authority_transfer(ctx: Context<TransferAuthority>) -> Result<()> {
         let state = &mut ctx.accounts.state;
+        let cancelled_authority = state.cancel_authority_transfer()?;
+
+        emit!(AuthorityTransferCancelled {
+            state: state.key(),
+            authority: state.authority,
+            cancelled_authority,
+        });
+        Ok(())
+    }
+
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        let previous_authority = state.accept_authority(&ctx.accounts.new_authority.key())?;
+
+        emit!(AuthorityTransferred {
+            state: state.key(),
+            previous_authority,
+            new_authority: state.authority,
+            time: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Creates the roles registry with the multisig signer as first admin
+    pub fn initialize_roles(ctx: Context<InitializeRoles>) -> Result<()> {
+        let roles = &mut ctx.accounts.roles;
+        roles.members = Vec::new();
+        let multisig_signer = multisig::multisig_signer();
+        roles.grant(Role::Admin, multisig_signer).map_err(TokenError::from)?;
+
+        emit!(RoleGranted {
+            registry: roles.key(),
+            role: Role::Admin,
+            member: multisig_signer,
+            admin: ctx.accounts.authority.key(),
+        });
+        Ok(())
+    }
+
+    pub fn grant_role(ctx: Context<ManageRoles>, role: Role, member: Pubkey) -> Result<()> {
+        let roles = &mut ctx.accounts.roles;
+        roles.grant(role, member).map_err(TokenError::from)?;
+
+        emit!(RoleGranted {
+            registry: roles.key(),
+            role,
+            member,
+            admin: ctx.accounts.admin.key(),
+        });
+        Ok(())
+    }
+
+    pub fn revoke_role(ctx: Context<ManageRoles>, role: Role, member: Pubkey) -> Result<()> {
+        let roles = &mut ctx.accounts.roles;
+        roles.revoke(role, &member).map_err(TokenError::from)?;
+
+        emit!(RoleRevoked {
+            registry: roles.key(),
+            role,
+            member,
+            admin: ctx.accounts.admin.key(),
+        });
+        Ok(())
+    }
+
+    pub fn unlock(ctx: Context<Unlock>, allocation: u8) -> Result<()> {
+        let schedule = &mut ctx.accounts.schedule;
         let current_time = Clock::get()?.unix_timestamp;
-        let days_since_tge = (current_time - state.tge_time) as u64 / (24 * 60 * 60); // Приводим к u64
+        let total_unlocked = schedule.vested_amount(current_time);
 
-        let unlock_interval = 180u64; // 6 месяцев
-        let unlock_amount_per_period = state.team_supply / 4; // 25% каждые 6 месяцев
-        let periods_passed = days_since_tge / unlock_interval;
-        let total_unlocked = unlock_amount_per_period * periods_passed.min(4u64); // Используем u64 для min
+        require!(total_unlocked > schedule.unlocked_amount, TokenError::NoTokensToUnlock);
 
-        require!(total_unlocked > state.team_unlocked, TokenError::NoTokensToUnlock);
+        let amount_to_unlock = total_unlocked - schedule.unlocked_amount;
+        ctx.accounts.ledger.record_mint(SupplyAllocation::Vesting, amount_to_unlock)?;
 
-        let amount_to_unlock = total_unlocked - state.team_unlocked;
         let cpi_accounts = token::MintTo {
             mint: ctx.accounts.mint.to_account_info(),
             to: ctx.accounts.token_account.to_account_info(),
-            authority: ctx.accounts.authority.to_account_info(),
+            authority: ctx.accounts.mint_authority.to_account_info(),
         };
         let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        let seeds = &[
+            b"mint_authority".as_ref(),
+            &[ctx.bumps.mint_authority],
+        ];
+        let signer = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
         token::mint_to(cpi_ctx, amount_to_unlock)?;
 
-        state.team_unlocked += amount_to_unlock;
+        schedule.unlocked_amount = total_unlocked;
+
+        emit!(TokensUnlocked {
+            allocation,
+            receiver: ctx.accounts.token_account.key(),
+            amount: amount_to_unlock,
+            total_unlocked: schedule.unlocked_amount,
+            time: current_time,
+        });
         Ok(())
     }
 
-    pub fn unlock_development_tokens(ctx: Context<UnlockTokens>) -> Result<()> {
+    pub fn add_dex_liquidity(ctx: Context<UnlockTokens>, amount: u64) -> Result<()> {
         let state = &mut ctx.accounts.state;
-        let current_time = Clock::get()?.unix_timestamp;
-        let days_since_tge = (current_time - state.tge_time) as u64 / (24 * 60 * 60); // Приводим к u64
-
-        let unlock_interval = 180u64; // 180 дней
-        let unlock_amount_per_period = state.development_supply / 6; // 500 млн за период
-        let periods_passed = (days_since_tge / unlock_interval + 1).min(6u64); // Включаем TGE, используем u64
-        let total_unlocked = unlock_amount_per_period * periods_passed;
-
-        require!(total_unlocked > state.development_unlocked, TokenError::NoTokensToUnlock);
+        require!(amount <= state.dex_liquidity_supply, TokenError::ExceedsDexLiquidityLimit);
+        ctx.accounts.ledger.record_mint(SupplyAllocation::DexLiquidity, amount)?;
 
-        let amount_to_unlock = total_unlocked - state.development_unlocked;
         let cpi_accounts = token::MintTo {
             mint: ctx.accounts.mint.to_account_info(),
             to: ctx.accounts.token_account.to_account_info(),
-            authority: ctx.accounts.authority.to_account_info(),
+            authority: ctx.accounts.mint_authority.to_account_info(),
         };
         let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-        token::mint_to(cpi_ctx, amount_to_unlock)?;
+        let seeds = &[
+            b"mint_authority".as_ref(),
+            &[ctx.bumps.mint_authority],
+        ];
+        let signer = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::mint_to(cpi_ctx, amount)?;
 
-        state.development_unlocked += amount_to_unlock;
+        state.dex_liquidity_supply = state
+            .dex_liquidity_supply
+            .checked_sub(amount)
+            .ok_or(TokenError::MathOverflow)?;
         Ok(())
     }
 
-    pub fn unlock_community_tokens(ctx: Context<UnlockTokens>) -> Result<()> {
+    pub fn release_cex_marketing(ctx: Context<UnlockTokens>, amount: u64) -> Result<()> {
         let state = &mut ctx.accounts.state;
-        let current_time = Clock::get()?.unix_timestamp;
-        let days_since_tge = (current_time - state.tge_time) as u64 / (24 * 60 * 60); // Приводим к u64
-
-        let unlock_interval = 60u64; // 60 дней
-        let periods_passed = (days_since_tge / unlock_interval + 1).min(5u64); // Включаем TGE, макс 5
-        let total_unlocked = if periods_passed == 1 {
-            state.community_supply * 30 / 100 // 300 млн на TGE
-        } else {
-            let base_unlocked = state.community_supply * 30 / 100;
-            let additional_unlocked = (periods_passed - 1) * 200_000_000u64 * 10u64.pow(6);
-            base_unlocked.checked_add(additional_unlocked).unwrap_or(state.community_supply)
-        }.min(state.community_supply);
+        let available = state
+            .cex_marketing_unlocked
+            .checked_sub(state.cex_marketing_drawn)
+            .ok_or(TokenError::MathOverflow)?;
+        require!(amount <= available, TokenError::ExceedsCexMarketingUnlocked);
+        ctx.accounts.ledger.record_mint(SupplyAllocation::CexMarketing, amount)?;
 
-        require!(total_unlocked > state.community_unlocked, TokenError::NoTokensToUnlock);
-
-        let amount_to_unlock = total_unlocked - state.community_unlocked; // Исправлено с team_unlocked на community_unlocked
         let cpi_accounts = token::MintTo {
             mint: ctx.accounts.mint.to_account_info(),
             to: ctx.accounts.token_account.to_account_info(),
-            authority: ctx.accounts.authority.to_account_info(),
+            authority: ctx.accounts.mint_authority.to_account_info(),
         };
         let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-        token::mint_to(cpi_ctx, amount_to_unlock)?;
+        let seeds = &[
+            b"mint_authority".as_ref(),
+            &[ctx.bumps.mint_authority],
+        ];
+        let signer = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::mint_to(cpi_ctx, amount)?;
 
-        state.community_unlocked += amount_to_unlock;
+        state.cex_marketing_drawn = state
+            .cex_marketing_drawn
+            .checked_add(amount)
+            .ok_or(TokenError::MathOverflow)?;
+
+        emit!(CexMarketingReleased {
+            receiver: ctx.accounts.token_account.key(),
+            amount,
+            total_drawn: state.cex_marketing_drawn,
+            remaining: available - amount,
+            time: Clock::get()?.unix_timestamp,
+        });
         Ok(())
     }
 
-    pub fn add_dex_liquidity(ctx: Context<UnlockTokens>, amount: u64) -> Result<()> {
-        let state = &mut ctx.accounts.state;
-        require!(amount <= state.dex_liquidity_supply, TokenError::ExceedsDexLiquidityLimit);
+    /// Called by the presale program, signed by its `presale_state` PDA
+    pub fn mint_presale_tokens(ctx: Context<MintPresaleTokens>, amount: u64) -> Result<()> {
+        ctx.accounts.ledger.record_mint(SupplyAllocation::Presale, amount)?;
 
         let cpi_accounts = token::MintTo {
             mint: ctx.accounts.mint.to_account_info(),
             to: ctx.accounts.token_account.to_account_info(),
-            authority: ctx.accounts.authority.to_account_info(),
+            authority: ctx.accounts.mint_authority.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let seeds = &[
+            b"mint_authority".as_ref(),
+            &[ctx.bumps.mint_authority],
+        ];
+        let signer = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::mint_to(cpi_ctx, amount)
+    }
+
+    /// Minted amount against the cap of every allocation, with a breakdown of
+    /// the vesting schedules passed as remaining accounts
+    pub fn report_supply<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ReportSupply<'info>>,
+    ) -> Result<SupplyReport> {
+        let ledger = &ctx.accounts.ledger;
+        let mut schedules = Vec::with_capacity(ctx.remaining_accounts.len());
+        for account in ctx.remaining_accounts.iter() {
+            let schedule = Account::<VestingSchedule>::try_from(account)?;
+            schedules.push(ScheduleSupply {
+                allocation: schedule.allocation,
+                cap: schedule.total_amount,
+                minted: schedule.unlocked_amount,
+            });
+        }
+
+        Ok(SupplyReport {
+            total_cap: ledger.total_cap,
+            total_minted: ledger.total_minted,
+            vesting: ledger.vesting,
+            dex_liquidity: ledger.dex_liquidity,
+            cex_marketing: ledger.cex_marketing,
+            presale: ledger.presale,
+            schedules,
+        })
+    }
+
+    pub fn create_multisig(ctx: Context<CreateMultisig>, owners: Vec<Pubkey>, threshold: u8) -> Result<()> {
+        require!(Multisig::is_valid_owner_set(&owners, threshold), TokenError::InvalidMultisigOwners);
+
+        let multisig = &mut ctx.accounts.multisig;
+        multisig.owners = owners;
+        multisig.threshold = threshold;
+        multisig.owner_set_seqno = 0;
+        multisig.proposal_count = 0;
+
+        emit!(MultisigOwnersSet {
+            owners: multisig.owners.clone(),
+            threshold,
+            owner_set_seqno: 0,
+        });
+        Ok(())
+    }
+
+    /// Only callable through an executed proposal, open proposals are voided
+    pub fn set_multisig_owners(ctx: Context<SetMultisigOwners>, owners: Vec<Pubkey>, threshold: u8) -> Result<()> {
+        require!(Multisig::is_valid_owner_set(&owners, threshold), TokenError::InvalidMultisigOwners);
+
+        let multisig = &mut ctx.accounts.multisig;
+        multisig.owners = owners;
+        multisig.threshold = threshold;
+        multisig.owner_set_seqno = multisig
+            .owner_set_seqno
+            .checked_add(1)
+            .ok_or(TokenError::MathOverflow)?;
+
+        emit!(MultisigOwnersSet {
+            owners: multisig.owners.clone(),
+            threshold,
+            owner_set_seqno: multisig.owner_set_seqno,
+        });
+        Ok(())
+    }
+
+    /// Proposes an instruction of either program, approved by the proposer
+    pub fn create_proposal(
+        ctx: Context<CreateProposal>,
+        program_id: Pubkey,
+        accounts: Vec<ProposalAccount>,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        let multisig = &mut ctx.accounts.multisig;
+        let owner_index = multisig
+            .owner_index(&ctx.accounts.proposer.key())
+            .ok_or(TokenError::NotMultisigOwner)?;
+
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.index = multisig.proposal_count;
+        proposal.proposer = ctx.accounts.proposer.key();
+        proposal.program_id = program_id;
+        proposal.accounts = accounts;
+        proposal.data = data;
+        proposal.approvals = vec![false; multisig.owners.len()];
+        proposal.approvals[owner_index] = true;
+        proposal.owner_set_seqno = multisig.owner_set_seqno;
+        proposal.executed = false;
+
+        multisig.proposal_count = multisig
+            .proposal_count
+            .checked_add(1)
+            .ok_or(TokenError::MathOverflow)?;
+
+        emit!(ProposalCreated {
+            index: proposal.index,
+            proposer: proposal.proposer,
+            program_id,
+        });
+        Ok(())
+    }
+
+    pub fn approve_proposal(ctx: Context<ApproveProposal>, index: u64) -> Result<()> {
+        let multisig = &ctx.accounts.multisig;
+        let proposal = &mut ctx.accounts.proposal;
+        let owner_index = multisig
+            .owner_index(&ctx.accounts.owner.key())
+            .ok_or(TokenError::NotMultisigOwner)?;
+
+        require!(!proposal.executed, TokenError::ProposalAlreadyExecuted);
+        require!(proposal.owner_set_seqno == multisig.owner_set_seqno, TokenError::StaleProposal);
+        proposal.approvals[owner_index] = true;
+
+        emit!(ProposalApproved {
+            index,
+            owner: ctx.accounts.owner.key(),
+            approvals: proposal.approval_count() as u8,
+            threshold: multisig.threshold,
+        });
+        Ok(())
+    }
+
+    /// Executes an approved proposal, the accounts of its instruction and the
+    /// target program are passed as remaining accounts
+    pub fn execute_proposal<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExecuteProposal<'info>>,
+        index: u64,
+    ) -> Result<()> {
+        let multisig = &ctx.accounts.multisig;
+        let proposal = &mut ctx.accounts.proposal;
+        require!(multisig.owner_index(&ctx.accounts.executor.key()).is_some(), TokenError::NotMultisigOwner);
+        require!(!proposal.executed, TokenError::ProposalAlreadyExecuted);
+        require!(proposal.is_approved(multisig), TokenError::ProposalNotApproved);
+        proposal.executed = true;
+
+        let instruction = proposal.instruction(&ctx.accounts.multisig_signer.key());
+        let seeds = &[
+            b"multisig_signer".as_ref(),
+            &[ctx.bumps.multisig_signer],
+        ];
+        let signer = &[&seeds[..]];
+        invoke_signed(&instruction, ctx.remaining_accounts, signer)?;
+
+        emit!(ProposalExecuted {
+            index,
+            executor: ctx.accounts.executor.key(),
+            time: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn create_timelock(ctx: Context<CreateTimelock>, admin: Pubkey, min_delay: i64) -> Result<()> {
+        let timelock = &mut ctx.accounts.timelock;
+        timelock.configure(admin, min_delay)?;
+        timelock.action_count = 0;
+
+        emit!(TimelockConfigSet { admin, min_delay });
+        Ok(())
+    }
+
+    /// Only callable through an executed action, so delay changes are queued too
+    pub fn set_timelock_config(ctx: Context<SetTimelockConfig>, admin: Pubkey, min_delay: i64) -> Result<()> {
+        ctx.accounts.timelock.configure(admin, min_delay)?;
+
+        emit!(TimelockConfigSet { admin, min_delay });
+        Ok(())
+    }
+
+    /// Queues an instruction of either program, executable `delay` seconds from now
+    pub fn queue_action(
+        ctx: Context<QueueAction>,
+        delay: i64,
+        program_id: Pubkey,
+        accounts: Vec<ProposalAccount>,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        let timelock = &mut ctx.accounts.timelock;
+        let current_time = Clock::get()?.unix_timestamp;
+
+        let action = &mut ctx.accounts.action;
+        action.index = timelock.action_count;
+        action.eta = timelock.eta(current_time, delay)?;
+        action.program_id = program_id;
+        action.accounts = accounts;
+        action.data = data;
+        action.executed = false;
+
+        timelock.action_count = timelock
+            .action_count
+            .checked_add(1)
+            .ok_or(TokenError::MathOverflow)?;
+
+        emit!(ActionQueued {
+            index: action.index,
+            program_id,
+            data: action.data.clone(),
+            eta: action.eta,
+        });
+        Ok(())
+    }
+
+    /// Executes a queued action once its eta has passed, the accounts of its
+    /// instruction and the target program are passed as remaining accounts
+    pub fn execute_action<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExecuteAction<'info>>,
+        index: u64,
+    ) -> Result<()> {
+        let action = &mut ctx.accounts.action;
+        let current_time = Clock::get()?.unix_timestamp;
+        action.check_executable(current_time)?;
+        action.executed = true;
+
+        let instruction = action.instruction(&ctx.accounts.timelock_signer.key());
+        let seeds = &[
+            b"timelock_signer".as_ref(),
+            &[ctx.bumps.timelock_signer],
+        ];
+        let signer = &[&seeds[..]];
+        invoke_signed(&instruction, ctx.remaining_accounts, signer)?;
+
+        emit!(ActionExecuted {
+            index,
+            time: current_time,
+        });
+        Ok(())
+    }
+
+    pub fn cancel_action(ctx: Context<CancelAction>, index: u64) -> Result<()> {
+        require!(!ctx.accounts.action.executed, TokenError::ActionAlreadyExecuted);
+
+        emit!(ActionCancelled {
+            index,
+            time: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn migrate_mint_authority(ctx: Context<MigrateMintAuthority>) -> Result<()> {
+        // Tokens the admin key already minted count against the total cap
+        ctx.accounts.ledger.record_existing_supply(ctx.accounts.mint.supply)?;
+
+        // Hand the mint over from the admin key to the program PDA
+        let cpi_accounts = token::SetAuthority {
+            current_authority: ctx.accounts.authority.to_account_info(),
+            account_or_mint: ctx.accounts.mint.to_account_info(),
         };
         let cpi_program = ctx.accounts.token_program.to_account_info();
         let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-        token::mint_to(cpi_ctx, amount)?;
+        token::set_authority(
+            cpi_ctx,
+            AuthorityType::MintTokens,
+            Some(ctx.accounts.mint_authority.key()),
+        )?;
 
-        state.dex_liquidity_supply -= amount;
+        emit!(MintAuthorityMigrated {
+            mint: ctx.accounts.mint.key(),
+            previous_authority: ctx.accounts.authority.key(),
+            new_authority: ctx.accounts.mint_authority.key(),
+        });
         Ok(())
     }
 }
 
 #[derive(Accounts)]
+#[instruction(total_supply: u64, config: AllocationConfig)]
 pub struct Initialize<'info> {
     #[account(
         init,
         payer = payer,
-        mint::decimals = 6,
-        mint::authority = authority,
+        mint::decimals = config.decimals,
+        mint::authority = mint_authority,
     )]
     pub mint: Account<'info, Mint>,
+    /// CHECK: PDA that signs every mint, holds no data
+    #[account(
+        seeds = [b"mint_authority"],
+        bump
+    )]
+    pub mint_authority: UncheckedAccount<'info>,
     #[account(mut)]
     pub authority: Signer<'info>,
     #[account(mut)]
@@ -154,40 +623,565 @@ pub struct Initialize<'info> {
     #[account(
         init,
         payer = payer,
-        space = 8 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8,
+        space = 8 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + AllocationConfig::LEN,
         seeds = [b"state"],
         bump
     )]
     pub state: Account<'info, TokenState>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + SupplyLedger::LEN,
+        seeds = [b"ledger"],
+        bump
+    )]
+    pub ledger: Account<'info, SupplyLedger>,
 }
 
 #[derive(Accounts)]
 pub struct UnlockTokens<'info> {
-    #[account(mut)]
+    #[account(
+        mut,
+        address = state.mint,
+    )]
     pub mint: Account<'info, Mint>,
+    /// CHECK: PDA mint authority
+    #[account(
+        seeds = [b"mint_authority"],
+        bump
+    )]
+    pub mint_authority: UncheckedAccount<'info>,
     #[account(mut)]
     pub token_account: Account<'info, TokenAccount>,
     #[account(mut)]
+    pub treasurer: Signer<'info>,
+    #[account(
+        seeds = [b"roles"],
+        bump,
+        constraint = roles.has_role(Role::Treasurer, &treasurer.key()) @ TokenError::MissingRole,
+    )]
+    pub roles: Account<'info, RoleRegistry>,
+    pub token_program: Program<'info, Token>,
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump,
+    )]
+    pub state: Account<'info, TokenState>,
+    #[account(
+        mut,
+        seeds = [b"ledger"],
+        bump
+    )]
+    pub ledger: Account<'info, SupplyLedger>,
+}
+
+#[derive(Accounts)]
+pub struct MintPresaleTokens<'info> {
+    #[account(
+        seeds = [b"presale"],
+        bump,
+        seeds::program = presale::ID,
+    )]
+    pub presale_signer: Signer<'info>,
+    #[account(
+        seeds = [b"state"],
+        bump
+    )]
+    pub state: Account<'info, TokenState>,
+    #[account(
+        mut,
+        seeds = [b"ledger"],
+        bump
+    )]
+    pub ledger: Account<'info, SupplyLedger>,
+    #[account(
+        mut,
+        address = state.mint,
+    )]
+    pub mint: Account<'info, Mint>,
+    /// CHECK: PDA mint authority
+    #[account(
+        seeds = [b"mint_authority"],
+        bump
+    )]
+    pub mint_authority: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        token::mint = mint,
+    )]
+    pub token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CreateMultisig<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"state"],
+        bump,
+        has_one = authority,
+    )]
+    pub state: Account<'info, TokenState>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Multisig::LEN,
+        seeds = [b"multisig"],
+        bump
+    )]
+    pub multisig: Account<'info, Multisig>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetMultisigOwners<'info> {
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump
+    )]
+    pub multisig: Account<'info, Multisig>,
+    #[account(
+        seeds = [b"multisig_signer"],
+        bump
+    )]
+    pub multisig_signer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(program_id: Pubkey, accounts: Vec<ProposalAccount>, data: Vec<u8>)]
+pub struct CreateProposal<'info> {
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump
+    )]
+    pub multisig: Account<'info, Multisig>,
+    #[account(
+        init,
+        payer = proposer,
+        space = MultisigProposal::space(accounts.len(), data.len()),
+        constraint = accounts.len() <= MAX_PROPOSAL_ACCOUNTS && data.len() <= MAX_PROPOSAL_DATA
+            @ TokenError::ProposalTooLarge,
+        seeds = [b"proposal".as_ref(), &multisig.proposal_count.to_le_bytes()],
+        bump
+    )]
+    pub proposal: Account<'info, MultisigProposal>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u64)]
+pub struct ApproveProposal<'info> {
+    pub owner: Signer<'info>,
+    #[account(
+        seeds = [b"multisig"],
+        bump
+    )]
+    pub multisig: Account<'info, Multisig>,
+    #[account(
+        mut,
+        seeds = [b"proposal".as_ref(), &index.to_le_bytes()],
+        bump
+    )]
+    pub proposal: Account<'info, MultisigProposal>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u64)]
+pub struct ExecuteProposal<'info> {
+    pub executor: Signer<'info>,
+    #[account(
+        seeds = [b"multisig"],
+        bump
+    )]
+    pub multisig: Account<'info, Multisig>,
+    #[account(
+        mut,
+        seeds = [b"proposal".as_ref(), &index.to_le_bytes()],
+        bump
+    )]
+    pub proposal: Account<'info, MultisigProposal>,
+    /// CHECK: PDA that signs executed proposals, holds no data
+    #[account(
+        seeds = [b"multisig_signer"],
+        bump
+    )]
+    pub multisig_signer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreateTimelock<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"state"],
+        bump,
+        has_one = authority,
+    )]
+    pub state: Account<'info, TokenState>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Timelock::LEN,
+        seeds = [b"timelock"],
+        bump
+    )]
+    pub timelock: Account<'info, Timelock>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetTimelockConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"timelock"],
+        bump
+    )]
+    pub timelock: Account<'info, Timelock>,
+    #[account(
+        seeds = [b"timelock_signer"],
+        bump
+    )]
+    pub timelock_signer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(delay: i64, program_id: Pubkey, accounts: Vec<ProposalAccount>, data: Vec<u8>)]
+pub struct QueueAction<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"timelock"],
+        bump,
+        has_one = admin,
+    )]
+    pub timelock: Account<'info, Timelock>,
+    #[account(
+        init,
+        payer = payer,
+        space = QueuedAction::space(accounts.len(), data.len()),
+        constraint = QueuedAction::fits(accounts.len(), data.len()) @ TokenError::ProposalTooLarge,
+        seeds = [b"queued_action".as_ref(), &timelock.action_count.to_le_bytes()],
+        bump
+    )]
+    pub action: Account<'info, QueuedAction>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u64)]
+pub struct ExecuteAction<'info> {
+    #[account(
+        mut,
+        seeds = [b"queued_action".as_ref(), &index.to_le_bytes()],
+        bump
+    )]
+    pub action: Account<'info, QueuedAction>,
+    /// CHECK: PDA that signs executed actions, holds no data
+    #[account(
+        seeds = [b"timelock_signer"],
+        bump
+    )]
+    pub timelock_signer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u64)]
+pub struct CancelAction<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"timelock"],
+        bump,
+        has_one = admin,
+    )]
+    pub timelock: Account<'info, Timelock>,
+    #[account(
+        mut,
+        close = rent_receiver,
+        seeds = [b"queued_action".as_ref(), &index.to_le_bytes()],
+        bump
+    )]
+    pub action: Account<'info, QueuedAction>,
+    /// CHECK: Receives the rent of the cancelled action
+    #[account(mut)]
+    pub rent_receiver: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReportSupply<'info> {
+    #[account(
+        seeds = [b"ledger"],
+        bump
+    )]
+    pub ledger: Account<'info, SupplyLedger>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateMintAuthority<'info> {
+    #[account(
+        mut,
+        address = state.mint,
+    )]
+    pub mint: Account<'info, Mint>,
+    /// CHECK: PDA that becomes the mint authority
+    #[account(
+        seeds = [b"mint_authority"],
+        bump
+    )]
+    pub mint_authority: UncheckedAccount<'info>,
     pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"state"],
+        bump,
+        has_one = authority,
+    )]
+    pub state: Account<'info, TokenState>,
+    #[account(
+        mut,
+        seeds = [b"ledger"],
+        bump
+    )]
+    pub ledger: Account<'info, SupplyLedger>,
     pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct TransferAuthority<'info> {
+    #[account(
+        seeds = [b"timelock_signer"],
+        bump
+    )]
+    pub timelock_signer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump,
+    )]
+    pub state: Account<'info, TokenState>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeRoles<'info> {
     #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"state"],
+        bump,
+        has_one = authority,
+    )]
+    pub state: Account<'info, TokenState>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + RoleRegistry::LEN,
+        seeds = [b"roles"],
+        bump
+    )]
+    pub roles: Account<'info, RoleRegistry>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ManageRoles<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"roles"],
+        bump,
+        constraint = roles.has_role(Role::Admin, &admin.key()) @ TokenError::MissingRole,
+    )]
+    pub roles: Account<'info, RoleRegistry>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    pub new_authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump,
+    )]
+    pub state: Account<'info, TokenState>,
+}
+
+#[derive(Accounts)]
+#[instruction(allocation: u8)]
+pub struct AddVestingSchedule<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        seeds = [b"timelock_signer"],
+        bump
+    )]
+    pub timelock_signer: Signer<'info>,
+    #[account(
+        seeds = [b"state"],
+        bump,
+    )]
     pub state: Account<'info, TokenState>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + 1 + 8 + 8 + 8 + VestingTerms::LEN + 8 + 32 + 32 + 8,
+        seeds = [b"vesting".as_ref(), &[allocation]],
+        bump
+    )]
+    pub schedule: Account<'info, VestingSchedule>,
+    #[account(token::mint = state.mint)]
+    pub beneficiary: Account<'info, TokenAccount>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(allocation: u8)]
+pub struct ProposeBeneficiary<'info> {
+    #[account(
+        seeds = [b"timelock_signer"],
+        bump
+    )]
+    pub timelock_signer: Signer<'info>,
+    #[account(
+        seeds = [b"state"],
+        bump,
+    )]
+    pub state: Account<'info, TokenState>,
+    #[account(
+        mut,
+        seeds = [b"vesting".as_ref(), &[allocation]],
+        bump
+    )]
+    pub schedule: Account<'info, VestingSchedule>,
+    #[account(token::mint = state.mint)]
+    pub new_beneficiary: Account<'info, TokenAccount>,
+}
+
+#[derive(Accounts)]
+#[instruction(allocation: u8)]
+pub struct ApplyBeneficiaryChange<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"state"],
+        bump,
+        has_one = authority,
+    )]
+    pub state: Account<'info, TokenState>,
+    #[account(
+        mut,
+        seeds = [b"vesting".as_ref(), &[allocation]],
+        bump
+    )]
+    pub schedule: Account<'info, VestingSchedule>,
+}
+
+#[derive(Accounts)]
+#[instruction(allocation: u8)]
+pub struct Unlock<'info> {
+    #[account(
+        mut,
+        address = state.mint,
+    )]
+    pub mint: Account<'info, Mint>,
+    /// CHECK: PDA mint authority
+    #[account(
+        seeds = [b"mint_authority"],
+        bump
+    )]
+    pub mint_authority: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = schedule.beneficiary @ TokenError::InvalidBeneficiary,
+    )]
+    pub token_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub operator: Signer<'info>,
+    #[account(
+        seeds = [b"roles"],
+        bump,
+        constraint = roles.has_role(Role::Operator, &operator.key()) @ TokenError::MissingRole,
+    )]
+    pub roles: Account<'info, RoleRegistry>,
+    pub token_program: Program<'info, Token>,
+    #[account(
+        seeds = [b"state"],
+        bump,
+    )]
+    pub state: Account<'info, TokenState>,
+    #[account(
+        mut,
+        seeds = [b"vesting".as_ref(), &[allocation]],
+        bump
+    )]
+    pub schedule: Account<'info, VestingSchedule>,
+    #[account(
+        mut,
+        seeds = [b"ledger"],
+        bump
+    )]
+    pub ledger: Account<'info, SupplyLedger>,
 }
 
 #[account]
 pub struct TokenState {
     pub authority: Pubkey,
+    pub pending_authority: Pubkey, // Default when no handover is pending
+    pub mint: Pubkey,
     pub total_supply: u64,
-    pub team_supply: u64,
-    pub team_unlocked: u64,
     pub dex_liquidity_supply: u64,
     pub cex_marketing_supply: u64,
     pub cex_marketing_unlocked: u64,
-    pub development_supply: u64,
-    pub development_unlocked: u64,
-    pub community_supply: u64,
-    pub community_unlocked: u64,
+    pub cex_marketing_drawn: u64,
+    pub vesting_supply: u64, // Shared by the vesting buckets of the config
     pub tge_time: i64,
+    pub config: AllocationConfig,
+}
+
+impl TokenState {
+    /// Starts a two-step handover, replacing any pending proposal
+    pub fn propose_authority(&mut self, new_authority: Pubkey) {
+        authority::propose(&mut self.pending_authority, new_authority);
+    }
+
+    /// Drops the pending handover and returns the key that was proposed
+    pub fn cancel_authority_transfer(&mut self) -> Result<Pubkey> {
+        Ok(authority::cancel(&mut self.pending_authority).map_err(TokenError::from)?)
+    }
+
+    /// Completes the handover when signed by the proposed key, returns the previous authority
+    pub fn accept_authority(&mut self, signer: &Pubkey) -> Result<Pubkey> {
+        Ok(authority::accept(&mut self.authority, &mut self.pending_authority, signer).map_err(TokenError::from)?)
+    }
+}
+
+#[account]
+pub struct VestingSchedule {
+    pub allocation: u8,
+    pub total_amount: u64,
+    pub amount_per_period: u64, // Zero splits the amount after TGE evenly
+    pub unlocked_amount: u64,
+    pub terms: VestingTerms,
+    pub start_time: i64,
+    pub beneficiary: Pubkey, // Only token account unlocks can be minted to
+    pub pending_beneficiary: Pubkey,
+    pub beneficiary_change_eta: i64, // Zero when no change is pending
+}
+
+impl VestingSchedule {
+    pub fn vested_amount(&self, current_time: i64) -> u64 {
+        let elapsed = current_time.saturating_sub(self.start_time);
+        if self.amount_per_period == 0 {
+            self.terms.vested_amount(self.total_amount, elapsed)
+        } else {
+            self.terms
+                .vested_amount_in_tranches(self.total_amount, elapsed, self.amount_per_period)
+        }
+    }
 }
 
 #[error_code]
@@ -196,5 +1190,77 @@ pub enum TokenError {
     NoTokensToUnlock,
     #[msg("Exceeds DEX liquidity limit")]
     ExceedsDexLiquidityLimit,
+    #[msg("Exceeds unlocked CEX/marketing balance")]
+    ExceedsCexMarketingUnlocked,
+    #[msg("Allocation has no vesting bucket in the config")]
+    UnknownAllocation,
+    #[msg("Token account is not the allocation beneficiary")]
+    InvalidBeneficiary,
+    #[msg("No beneficiary change is pending")]
+    NoPendingBeneficiary,
+    #[msg("Timelock has not expired yet")]
+    TimelockNotExpired,
+    #[msg("Mint would exceed the supply cap")]
+    SupplyCapExceeded,
+    #[msg("Arithmetic overflow")]
+    MathOverflow,
+    #[msg("Invalid allocation config")]
+    InvalidAllocation,
+    #[msg("No authority transfer is pending")]
+    NoPendingAuthority,
+    #[msg("Signer is not the pending authority")]
+    NotPendingAuthority,
+    #[msg("Invalid multisig owners or threshold")]
+    InvalidMultisigOwners,
+    #[msg("Signer is not a multisig owner")]
+    NotMultisigOwner,
+    #[msg("Proposal instruction is too large")]
+    ProposalTooLarge,
+    #[msg("Proposal was already executed")]
+    ProposalAlreadyExecuted,
+    #[msg("Proposal does not have enough approvals")]
+    ProposalNotApproved,
+    #[msg("Multisig owners changed since the proposal was created")]
+    StaleProposal,
+    #[msg("Delay is below the timelock minimum")]
+    DelayTooShort,
+    #[msg("Action is not executable before its eta")]
+    TooEarly,
+    #[msg("Action was already executed")]
+    ActionAlreadyExecuted,
+    #[msg("Signer does not have the required role")]
+    MissingRole,
+    #[msg("Role is already granted")]
+    RoleAlreadyGranted,
+    #[msg("Role is not granted")]
+    RoleNotGranted,
+    #[msg("Roles registry is full")]
+    RoleRegistryFull,
+    #[msg("The last admin cannot be revoked")]
+    LastAdmin,
+    #[msg("The timelock admin must be the multisig signer")]
+    InvalidTimelockAdmin,
+    #[msg("Queued action expired")]
+    ActionExpired,
+}
+
+impl From<AuthorityError> for TokenError {
+    fn from(error: AuthorityError) -> Self {
+        match error {
+            AuthorityError::NoPendingAuthority => TokenError::NoPendingAuthority,
+            AuthorityError::NotPendingAuthority => TokenError::NotPendingAuthority,
+        }
+    }
+}
+
+impl From<RoleError> for TokenError {
+    fn from(error: RoleError) -> Self {
+        match error {
+            RoleError::AlreadyGranted => TokenError::RoleAlreadyGranted,
+            RoleError::NotGranted => TokenError::RoleNotGranted,
+            RoleError::RegistryFull => TokenError::RoleRegistryFull,
+            RoleError::LastAdmin => TokenError::LastAdmin,
+        }
+    }
 }
 
@@ -0,0 +1,65 @@
+//! Tokenomics arithmetic shared by the token and presale programs.
+//! Every function is pure and returns `None` on overflow or division by zero,
+//! so each program can surface its own error.
+
+pub const SOUL_DECIMALS: u32 = 6;
+pub const LAMPORTS_PER_SOL: u128 = 1_000_000_000;
+/// USD amounts and prices are in micro-USD ($1 = 1_000_000)
+pub const MICRO_USD_PER_USD: u128 = 1_000_000;
+
+/// Whole tokens to base units
+pub fn to_base_units(amount: u64, decimals: u32) -> Option<u64> {
+    amount.checked_mul(10u64.checked_pow(decimals)?)
+}
+
+/// `bps` basis points of `amount`, rounded down
+pub fn bps_of(amount: u64, bps: u64) -> Option<u64> {
+    u64::try_from(amount as u128 * bps as u128 / 10_000).ok()
+}
+
+/// Sum of all `amounts`
+pub fn checked_sum(amounts: &[u64]) -> Option<u64> {
+    amounts.iter().try_fold(0u64, |sum, &amount| sum.checked_add(amount))
+}
+
+/// Token base units bought with `amount_usd` micro-USD at `price` micro-USD per
+/// whole token, rounded down so the buyer never receives more than was paid for
+pub fn tokens_for_usd(amount_usd: u64, price: u64, token_decimals: u32) -> Option<u64> {
+    let numerator = (amount_usd as u128).checked_mul(10u128.checked_pow(token_decimals)?)?;
+    u64::try_from(numerator.checked_div(price as u128)?).ok()
+}
+
+/// Micro-USD cost of `tokens` base units at `price` micro-USD per whole token,
+/// rounded up so the treasury never receives less than the tokens are worth
+pub fn usd_for_tokens(tokens: u64, price: u64, token_decimals: u32) -> Option<u64> {
+    let numerator = (tokens as u128).checked_mul(price as u128)?;
+    let denominator = 10u128.checked_pow(token_decimals)?;
+    u64::try_from(numerator.checked_add(denominator - 1)? / denominator).ok()
+}
+
+/// Base units of a USD stablecoin with `decimals` worth `amount_usd` micro-USD,
+/// rounded up so the treasury never receives less than the quoted amount
+pub fn usd_to_stablecoin(amount_usd: u64, decimals: u32) -> Option<u64> {
+    let numerator = (amount_usd as u128).checked_mul(10u128.checked_pow(decimals)?)?;
+    u64::try_from(numerator.checked_add(MICRO_USD_PER_USD - 1)? / MICRO_USD_PER_USD).ok()
+}
+
+/// Lamports worth `amount_usd` micro-USD at a SOL/USD price of `price * 10^expo`,
+/// rounded up so the treasury never receives less than the quoted amount
+pub fn usd_to_lamports(amount_usd: u64, price: i64, expo: i32) -> Option<u64> {
+    if price <= 0 {
+        return None;
+    }
+
+    // lamports = micro_usd * 10^9 / (10^6 * price * 10^expo)
+    let expo_scale = 10u128.checked_pow(expo.unsigned_abs())?;
+    let amount = (amount_usd as u128).checked_mul(LAMPORTS_PER_SOL)?;
+    let price = (price as u128).checked_mul(MICRO_USD_PER_USD)?;
+    let (numerator, denominator) = if expo < 0 {
+        (amount.checked_mul(expo_scale)?, price)
+    } else {
+        (amount, price.checked_mul(expo_scale)?)
+    };
+
+    u64::try_from(numerator.checked_add(denominator - 1)? / denominator).ok()
+}
@@ -0,0 +1,119 @@
+//! M-of-N admin multisig. Proposals carry an instruction for either program
+//! that is executed once enough owners approved it, signed by the
+//! `[b"multisig_signer"]` PDA. The PDA is the first roles admin of both
+//! programs, so the operator, treasurer and pauser keys are only granted or
+//! revoked once the threshold approves it. Configuration changes reach the
+//! programs through the timelock, whose admin is always this PDA.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+
+pub const MAX_MULTISIG_OWNERS: usize = 10;
+pub const MAX_PROPOSAL_ACCOUNTS: usize = 24;
+pub const MAX_PROPOSAL_DATA: usize = 512;
+
+/// Address of the `[b"multisig_signer"]` PDA of the token program
+pub fn multisig_signer() -> Pubkey {
+    Pubkey::find_program_address(&[b"multisig_signer"], &crate::ID).0
+}
+
+#[account]
+pub struct Multisig {
+    pub owners: Vec<Pubkey>,
+    pub threshold: u8,
+    pub owner_set_seqno: u32, // Bumped on every owner change, voids open proposals
+    pub proposal_count: u64,
+}
+
+impl Multisig {
+    pub const LEN: usize = 4 + 32 * MAX_MULTISIG_OWNERS + 1 + 4 + 8;
+
+    pub fn is_valid_owner_set(owners: &[Pubkey], threshold: u8) -> bool {
+        let unique = owners
+            .iter()
+            .enumerate()
+            .all(|(index, owner)| !owners[..index].contains(owner));
+        unique
+            && owners.len() <= MAX_MULTISIG_OWNERS
+            && threshold > 0
+            && threshold as usize <= owners.len()
+    }
+
+    pub fn owner_index(&self, owner: &Pubkey) -> Option<usize> {
+        self.owners.iter().position(|other| other == owner)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct ProposalAccount {
+    pub pubkey: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+impl ProposalAccount {
+    pub const LEN: usize = 32 + 1 + 1;
+}
+
+#[account]
+pub struct MultisigProposal {
+    pub index: u64,
+    pub proposer: Pubkey,
+    pub program_id: Pubkey,
+    pub accounts: Vec<ProposalAccount>,
+    pub data: Vec<u8>,
+    pub approvals: Vec<bool>, // One per owner, in the order of `Multisig::owners`
+    pub owner_set_seqno: u32,
+    pub executed: bool,
+}
+
+impl MultisigProposal {
+    pub fn space(accounts: usize, data: usize) -> usize {
+        8 + 8
+            + 32
+            + 32
+            + 4
+            + ProposalAccount::LEN * accounts
+            + 4
+            + data
+            + 4
+            + MAX_MULTISIG_OWNERS
+            + 4
+            + 1
+    }
+
+    pub fn approval_count(&self) -> usize {
+        self.approvals.iter().filter(|approved| **approved).count()
+    }
+
+    pub fn is_approved(&self, multisig: &Multisig) -> bool {
+        self.owner_set_seqno == multisig.owner_set_seqno
+            && self.approval_count() >= multisig.threshold as usize
+    }
+
+    /// The proposed instruction, with the multisig signer PDA marked as signer
+    pub fn instruction(&self, multisig_signer: &Pubkey) -> Instruction {
+        signed_instruction(self.program_id, &self.accounts, &self.data, multisig_signer)
+    }
+}
+
+/// Builds a stored instruction, marking the PDA that will sign it as signer
+pub fn signed_instruction(
+    program_id: Pubkey,
+    accounts: &[ProposalAccount],
+    data: &[u8],
+    signer: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: accounts
+            .iter()
+            .map(|account| AccountMeta {
+                pubkey: account.pubkey,
+                is_signer: account.is_signer || account.pubkey == *signer,
+                is_writable: account.is_writable,
+            })
+            .collect(),
+        data: data.to_vec(),
+    }
+}
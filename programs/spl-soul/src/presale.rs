@@ -1,12 +1,37 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{instruction::Instruction, program::invoke_signed};
+use anchor_lang::system_program;
+use anchor_lang::InstructionData;
 use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
 use anchor_spl::associated_token::AssociatedToken;
-use pyth_sdk_solana::load_price_feed_from_account_info;
+use pyth_sdk_solana::{load_price_feed_from_account_info, Price};
+use crate::allowlist;
+use crate::authority::{self, AuthorityError};
 use crate::events::*;
+use crate::math;
+use crate::multisig;
+use crate::roles::{Role, RoleError, RoleRegistry};
+use crate::vesting::VestingTerms;
+use crate::{SupplyLedger, TokenState};
 
 declare_id!("PRESALE_ID_PLACEHOLDER"); // Replace with real ID after generation
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+/// Oldest SOL/USD price accepted for a purchase, in seconds
+pub const MAX_PRICE_AGE_SECONDS: u64 = 60;
+/// Widest accepted confidence interval, in basis points of the price
+pub const MAX_PRICE_CONFIDENCE_BPS: u64 = 100;
+/// Most sale stages a presale can hold
+pub const MAX_STAGES: usize = 10;
+/// Most treasury wallets withdrawals can be sent to
+pub const MAX_TREASURY_DESTINATIONS: usize = 8;
+/// Most recipients a treasury split table can hold
+pub const MAX_SPLIT_RECIPIENTS: usize = 5;
+/// Length of a withdrawal allowance window
+pub const WITHDRAWAL_PERIOD: i64 = DAY;
+
+const DAY: i64 = 24 * 60 * 60;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
 pub enum PaymentCurrency {
     SOL,
     USDT,
@@ -16,10 +41,304 @@ pub enum PaymentCurrency {
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
 pub enum PresaleStage {
     NotStarted,
-    StageOne,
-    StageTwo,
-    StageThree,
+    Active, // Selling in `stages[current_stage]`
     Ended,
+    Refunding, // Ended below the soft cap, buyers reclaim their payments
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum CarryOver {
+    NextStage, // Unsold tokens are added to the next stage
+    Burn,      // Unsold tokens are left for `burn_remaining_tokens`
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct StageConfig {
+    pub price: u64, // In micro-USD per whole token ($0.005 = 5_000)
+    pub allocation: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub carry_over: CarryOver,
+}
+
+impl StageConfig {
+    pub const LEN: usize = 8 + 8 + 8 + 8 + 1;
+}
+
+/// Per-wallet limits, zero disables a limit
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PurchaseLimits {
+    pub max_tokens_per_wallet: u64,
+    pub max_tokens_per_wallet_per_stage: u64,
+    pub max_ticket_usd: u64, // In micro-USD
+}
+
+impl PurchaseLimits {
+    pub const LEN: usize = 8 + 8 + 8;
+}
+
+/// Withdrawals of one currency within the current window, capped at `period_limit`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WithdrawalAllowance {
+    pub period_limit: u64, // In base units of the currency, lamports for SOL
+    pub period_start: i64,
+    pub withdrawn: u64,
+}
+
+impl WithdrawalAllowance {
+    pub const LEN: usize = 8 + 8 + 8;
+
+    /// Records a withdrawal and returns what is left of the window's allowance.
+    /// A new window starts once `WITHDRAWAL_PERIOD` has passed since the last one.
+    pub fn withdraw(&mut self, amount: u64, now: i64) -> Result<u64> {
+        self.roll_period(now);
+        let withdrawn = self.withdrawn.checked_add(amount).ok_or(PresaleError::CalculationError)?;
+        require!(withdrawn <= self.period_limit, PresaleError::WithdrawalLimitExceeded);
+        self.withdrawn = withdrawn;
+        Ok(self.period_limit - withdrawn)
+    }
+
+    /// Records a withdrawal of as much of `amount` as the window's allowance
+    /// has left and returns the amount withdrawn
+    pub fn withdraw_up_to(&mut self, amount: u64, now: i64) -> u64 {
+        self.roll_period(now);
+        let amount = amount.min(self.period_limit.saturating_sub(self.withdrawn));
+        self.withdrawn += amount;
+        amount
+    }
+
+    fn roll_period(&mut self, now: i64) {
+        if now >= self.period_start.saturating_add(WITHDRAWAL_PERIOD) {
+            self.period_start = now;
+            self.withdrawn = 0;
+        }
+    }
+}
+
+/// Where treasury funds can be sent and how fast
+#[account]
+pub struct TreasuryPolicy {
+    pub destinations: Vec<Pubkey>, // Wallets, token withdrawals go to accounts they own
+    pub usdt: WithdrawalAllowance,
+    pub usdc: WithdrawalAllowance,
+    pub sol: WithdrawalAllowance,
+}
+
+impl TreasuryPolicy {
+    pub const LEN: usize = 4 + 32 * MAX_TREASURY_DESTINATIONS + WithdrawalAllowance::LEN * 3;
+
+    pub fn is_destination(&self, wallet: &Pubkey) -> bool {
+        self.destinations.contains(wallet)
+    }
+
+    pub fn allowance(&mut self, currency: PaymentCurrency) -> &mut WithdrawalAllowance {
+        match currency {
+            PaymentCurrency::SOL => &mut self.sol,
+            PaymentCurrency::USDT => &mut self.usdt,
+            PaymentCurrency::USDC => &mut self.usdc,
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct SplitShare {
+    pub wallet: Pubkey,
+    pub bps: u16,
+}
+
+/// A split recipient and what it can claim from the vaults
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct SplitRecipient {
+    pub wallet: Pubkey, // Token payouts go to accounts it owns
+    pub bps: u16,
+    pub owed_usdt: u64,
+    pub owed_usdc: u64,
+    pub owed_lamports: u64,
+}
+
+impl SplitRecipient {
+    pub const LEN: usize = 32 + 2 + 8 + 8 + 8;
+
+    fn owed(&mut self, currency: PaymentCurrency) -> &mut u64 {
+        match currency {
+            PaymentCurrency::SOL => &mut self.owed_lamports,
+            PaymentCurrency::USDT => &mut self.owed_usdt,
+            PaymentCurrency::USDC => &mut self.owed_usdc,
+        }
+    }
+}
+
+/// Routes every payment to fixed shares of the proceeds, empty when payments
+/// are only moved out with `withdraw_funds` and `withdraw_sol`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, PartialEq, Eq)]
+pub struct TreasurySplit {
+    pub recipients: Vec<SplitRecipient>,
+}
+
+impl TreasurySplit {
+    pub const LEN: usize = 4 + SplitRecipient::LEN * MAX_SPLIT_RECIPIENTS;
+
+    pub fn new(shares: &[SplitShare]) -> Result<Self> {
+        let unique = shares
+            .iter()
+            .enumerate()
+            .all(|(index, share)| shares[..index].iter().all(|other| other.wallet != share.wallet));
+        let total_bps: u32 = shares.iter().map(|share| share.bps as u32).sum();
+        require!(
+            shares.is_empty()
+                || (unique && shares.len() <= MAX_SPLIT_RECIPIENTS && total_bps == 10_000),
+            PresaleError::InvalidTreasurySplit
+        );
+
+        Ok(Self {
+            recipients: shares
+                .iter()
+                .map(|share| SplitRecipient {
+                    wallet: share.wallet,
+                    bps: share.bps,
+                    owed_usdt: 0,
+                    owed_usdc: 0,
+                    owed_lamports: 0,
+                })
+                .collect(),
+        })
+    }
+
+    /// Splits a payment across the recipients, the rounding remainder goes to the first one
+    pub fn credit(&mut self, currency: PaymentCurrency, amount: u64) -> Result<()> {
+        let mut credited = 0u64;
+        for recipient in self.recipients.iter_mut() {
+            let share = math::bps_of(amount, recipient.bps as u64).ok_or(PresaleError::CalculationError)?;
+            let owed = recipient.owed(currency);
+            *owed = owed.checked_add(share).ok_or(PresaleError::CalculationError)?;
+            credited += share;
+        }
+        if let Some(first) = self.recipients.first_mut() {
+            let owed = first.owed(currency);
+            *owed = owed.checked_add(amount - credited).ok_or(PresaleError::CalculationError)?;
+        }
+        Ok(())
+    }
+
+    /// Takes what recipient `index` can be paid out of the table, as (USDT, USDC,
+    /// lamports). Payouts are bound by the treasury policy like withdrawals:
+    /// the recipient has to be an allowlisted destination, and each currency
+    /// is paid up to what is left of its allowance, the rest stays owed.
+    /// Stablecoins are only paid when the recipient passed the matching account.
+    pub fn take_payout(
+        &mut self,
+        index: usize,
+        policy: &mut TreasuryPolicy,
+        pay_usdt: bool,
+        pay_usdc: bool,
+        now: i64,
+    ) -> Result<(u64, u64, u64)> {
+        let recipient = self.recipients.get_mut(index).ok_or(PresaleError::InvalidTreasurySplit)?;
+        require!(policy.is_destination(&recipient.wallet), PresaleError::InvalidDestination);
+
+        let mut payout = [0u64; 3];
+        let currencies = [
+            (PaymentCurrency::USDT, pay_usdt),
+            (PaymentCurrency::USDC, pay_usdc),
+            (PaymentCurrency::SOL, true),
+        ];
+        let mut owed_total = 0u64;
+        for (amount, &(currency, pay)) in payout.iter_mut().zip(currencies.iter()) {
+            let owed = recipient.owed(currency);
+            if !pay || *owed == 0 {
+                continue;
+            }
+            owed_total = owed_total.saturating_add(*owed);
+            *amount = policy.allowance(currency).withdraw_up_to(*owed, now);
+            *owed -= *amount;
+        }
+
+        require!(owed_total > 0, PresaleError::NothingToClaim);
+        require!(payout != [0; 3], PresaleError::WithdrawalLimitExceeded);
+        Ok((payout[0], payout[1], payout[2]))
+    }
+
+    /// Unclaimed total of a currency, held back from withdrawals
+    pub fn owed(&self, currency: PaymentCurrency) -> u64 {
+        self.recipients
+            .iter()
+            .map(|recipient| match currency {
+                PaymentCurrency::SOL => recipient.owed_lamports,
+                PaymentCurrency::USDT => recipient.owed_usdt,
+                PaymentCurrency::USDC => recipient.owed_usdc,
+            })
+            .sum()
+    }
+}
+
+/// Referral rewards in basis points of each referred purchase, a cap of zero is unlimited
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReferralConfig {
+    pub bonus_bps: u16,      // Bonus SOUL, of the tokens purchased
+    pub commission_bps: u16, // Commission on USDT/USDC purchases, of the amount paid
+    pub max_bonus_tokens: u64,
+    pub max_commission_usd: u64, // In micro-USD
+}
+
+impl ReferralConfig {
+    pub const LEN: usize = 2 + 2 + 8 + 8;
+
+    /// Bonus SOUL and commission in micro-USD earned by a purchase,
+    /// limited by what the referrer has left under the caps
+    pub fn rewards(&self, referrer: &ReferrerInfo, tokens: u64, amount_usd: u64) -> Result<(u64, u64)> {
+        let mut bonus_tokens = math::bps_of(tokens, self.bonus_bps as u64).ok_or(PresaleError::CalculationError)?;
+        if self.max_bonus_tokens > 0 {
+            bonus_tokens = bonus_tokens.min(self.max_bonus_tokens.saturating_sub(referrer.bonus_tokens));
+        }
+
+        let mut commission_usd =
+            math::bps_of(amount_usd, self.commission_bps as u64).ok_or(PresaleError::CalculationError)?;
+        if self.max_commission_usd > 0 {
+            commission_usd = commission_usd.min(self.max_commission_usd.saturating_sub(referrer.commission_usd));
+        }
+
+        Ok((bonus_tokens, commission_usd))
+    }
+}
+
+/// The three SOUL stages: 1 billion tokens each for 30 days, starting at $0.005
+/// with the price rising by 40% per stage and unsold tokens carried over
+pub fn soul_stages(start_time: i64) -> Vec<StageConfig> {
+    let allocation = 1_000_000_000 * 10u64.pow(math::SOUL_DECIMALS);
+    [5_000, 7_000, 9_800]
+        .iter()
+        .enumerate()
+        .map(|(index, &price)| {
+            let start_time = start_time + index as i64 * 30 * DAY;
+            StageConfig {
+                price,
+                allocation,
+                start_time,
+                end_time: start_time + 30 * DAY,
+                carry_over: CarryOver::NextStage,
+            }
+        })
+        .collect()
+}
+
+/// Stages must have a price, run for a positive time and not overlap
+pub fn stages_are_valid(stages: &[StageConfig]) -> bool {
+    if stages.len() > MAX_STAGES {
+        return false;
+    }
+
+    let mut previous_end = i64::MIN;
+    for stage in stages {
+        if stage.price == 0
+            || stage.allocation == 0
+            || stage.start_time >= stage.end_time
+            || stage.start_time < previous_end
+        {
+            return false;
+        }
+        previous_end = stage.end_time;
+    }
+    true
 }
 
 #[program]
@@ -28,54 +347,136 @@ pub mod soul_presale {
 
     pub fn initialize_presale(
         ctx: Context<InitializePresale>,
-        start_time: i64,
+        stages: Vec<StageConfig>,
+        vesting: VestingTerms,
+        soft_cap_usd: u64, // In micro-USD, zero for no soft cap
     ) -> Result<()> {
+        require!(vesting.is_valid(), PresaleError::InvalidVestingTerms);
+        require!(stages_are_valid(&stages), PresaleError::InvalidStageConfig);
+
         let presale = &mut ctx.accounts.presale_state;
         
         presale.authority = ctx.accounts.authority.key();
+        presale.pending_authority = Pubkey::default();
         presale.soul_mint = ctx.accounts.soul_mint.key();
         presale.usdt_mint = ctx.accounts.usdt_mint.key();
         presale.usdc_mint = ctx.accounts.usdc_mint.key();
         presale.treasury_wallet = ctx.accounts.treasury_wallet.key();
+        presale.sol_price_feed = ctx.accounts.sol_price_feed.key();
+        presale.soul_decimals = ctx.accounts.soul_mint.decimals;
+        presale.usdt_decimals = ctx.accounts.usdt_mint.decimals;
+        presale.usdc_decimals = ctx.accounts.usdc_mint.decimals;
+        presale.usdt_vault = ctx.accounts.usdt_vault.key();
+        presale.usdc_vault = ctx.accounts.usdc_vault.key();
         
         presale.stage = PresaleStage::NotStarted;
-        presale.current_stage_start = start_time;
-        presale.current_stage_end = start_time + 30 * 24 * 60 * 60; // 30 days
-        
-        // 1 billion tokens per stage with 6 decimals
-        presale.tokens_per_stage = 1_000_000_000 * 10u64.pow(6);
+        presale.current_stage = 0;
+        presale.stage_tokens_remaining = 0;
         presale.tokens_sold_current_stage = 0;
         presale.total_tokens_sold = 0;
+        presale.bonus_tokens_current_stage = 0;
+        presale.total_bonus_tokens = 0;
+        presale.unsold_tokens = 0;
+        presale.allowlist_root = [0; 32];
+        presale.allowlist_end_time = 0;
+        presale.limits = PurchaseLimits::default();
+        presale.referral = ReferralConfig::default();
+        presale.referral_owed_usdt = 0;
+        presale.referral_owed_usdc = 0;
         
-        // $0.005 = 0.5 cents
-        presale.base_price_usd = 50;
         presale.is_paused = false;
-        presale.min_purchase_amount = 5000; // $50 in cents
-        
-        // Initial allocation for each stage
-        presale.stage_one_allocation = presale.tokens_per_stage;
-        presale.stage_two_allocation = presale.tokens_per_stage;
-        presale.stage_three_allocation = presale.tokens_per_stage;
+        presale.min_purchase_amount = 50_000_000; // $50 in micro-USD
+
+        // More stages can be added with add_stage until the sale starts
+        presale.stages = stages;
+
+        // Purchased tokens vest from the end of the presale
+        presale.vesting = vesting;
+        presale.vesting_start = 0;
+
+        // Funds stay in the vaults until the soft cap is reached
+        presale.soft_cap_usd = soft_cap_usd;
+        presale.total_raised_usd = 0;
+        presale.treasury_split = TreasurySplit::default();
+
+        Ok(())
+    }
+
+    /// Replaces the split table, fixed once the pre-sale has started
+    pub fn set_treasury_split(ctx: Context<ConfigurePresale>, shares: Vec<SplitShare>) -> Result<()> {
+        let presale = &mut ctx.accounts.presale_state;
+        require!(presale.stage == PresaleStage::NotStarted, PresaleError::InvalidStage);
+
+        presale.treasury_split = TreasurySplit::new(&shares)?;
+        emit!(TreasurySplitUpdated { shares });
+        Ok(())
+    }
+
+    pub fn add_stage(ctx: Context<ConfigurePresale>, stage: StageConfig) -> Result<()> {
+        let presale = &mut ctx.accounts.presale_state;
+        require!(presale.stage == PresaleStage::NotStarted, PresaleError::InvalidStage);
+
+        presale.stages.push(stage);
+        require!(stages_are_valid(&presale.stages), PresaleError::InvalidStageConfig);
+        Ok(())
+    }
+
+    /// Restricts purchases before `end_time` to wallets in the Merkle tree,
+    /// a zero root disables the allowlist phase
+    pub fn set_allowlist(ctx: Context<ConfigurePresale>, root: [u8; 32], end_time: i64) -> Result<()> {
+        let presale = &mut ctx.accounts.presale_state;
+        require!(presale.stage == PresaleStage::NotStarted, PresaleError::InvalidStage);
 
+        presale.allowlist_root = root;
+        presale.allowlist_end_time = end_time;
+
+        emit!(AllowlistUpdated { root, end_time });
+        Ok(())
+    }
+
+    pub fn set_purchase_limits(ctx: Context<ConfigurePresale>, limits: PurchaseLimits) -> Result<()> {
+        let presale = &mut ctx.accounts.presale_state;
+        // Limits are fixed for the whole sale, every buyer is held to the same caps
+        require!(presale.stage == PresaleStage::NotStarted, PresaleError::InvalidStage);
+
+        presale.limits = limits;
+        Ok(())
+    }
+
+    pub fn set_referral_config(ctx: Context<ConfigurePresale>, referral: ReferralConfig) -> Result<()> {
+        require!(
+            referral.bonus_bps as u64 <= 10_000 && referral.commission_bps as u64 <= 10_000,
+            PresaleError::InvalidReferralConfig
+        );
+        ctx.accounts.presale_state.referral = referral;
+        Ok(())
+    }
+
+    pub fn register_referrer(ctx: Context<RegisterReferrer>) -> Result<()> {
+        let referrer_info = &mut ctx.accounts.referrer_info;
+        referrer_info.referrer = ctx.accounts.referrer.key();
         Ok(())
     }
 
     pub fn start_presale(ctx: Context<UpdatePresale>) -> Result<()> {
         let presale = &mut ctx.accounts.presale_state;
         require!(presale.stage == PresaleStage::NotStarted, PresaleError::InvalidStage);
+        let first_stage = *presale.stages.first().ok_or(PresaleError::InvalidStageConfig)?;
         
         let clock = Clock::get()?;
-        require!(clock.unix_timestamp >= presale.current_stage_start, PresaleError::TooEarly);
+        require!(clock.unix_timestamp >= first_stage.start_time, PresaleError::TooEarly);
         
-        presale.stage = PresaleStage::StageOne;
+        presale.stage = PresaleStage::Active;
+        presale.current_stage = 0;
+        presale.stage_tokens_remaining = first_stage.allocation;
 
         // Emit stage opening event
         emit!(StageOpened {
             stage: 1,
-            start_time: clock.unix_timestamp,
-            end_time: presale.current_stage_end,
-            token_allocation: presale.stage_one_allocation,
-            price: presale.base_price_usd,
+            start_time: first_stage.start_time,
+            end_time: first_stage.end_time,
+            token_allocation: presale.stage_tokens_remaining,
+            price: first_stage.price,
         });
 
         Ok(())
@@ -85,35 +486,38 @@ pub mod soul_presale {
         ctx: Context<PurchaseTokens>,
         currency: PaymentCurrency,
         amount_usd: u64,
+        min_tokens_out: u64,
+        max_price: u64, // In micro-USD per whole token
+        allowlist_allocation: u64, // Ignored after the allowlist phase
+        allowlist_proof: Vec<[u8; 32]>,
     ) -> Result<()> {
         let presale = &mut ctx.accounts.presale_state;
-        require!(!presale.is_paused, PresaleError::PresalePaused);
-        require!(amount_usd >= presale.min_purchase_amount, PresaleError::BelowMinimum);
-        
-        // Calculate price based on current stage
-        let price_multiplier = match presale.stage {
-            PresaleStage::StageOne => 100,
-            PresaleStage::StageTwo => 140,
-            PresaleStage::StageThree => 196,
-            _ => return Err(PresaleError::InvalidStage.into()),
-        };
-        
-        let tokens_to_purchase = (amount_usd as u128 * 10u128.pow(6) * 100)
-            .checked_div(presale.base_price_usd as u128 * price_multiplier as u128)
-            .ok_or(PresaleError::CalculationError)? as u64;
-            
-        let available_tokens = match presale.stage {
-            PresaleStage::StageOne => presale.stage_one_allocation,
-            PresaleStage::StageTwo => presale.stage_two_allocation,
-            PresaleStage::StageThree => presale.stage_three_allocation,
-            _ => return Err(PresaleError::InvalidStage.into()),
-        };
+        presale.validate_payment(
+            currency,
+            &ctx.accounts.buyer_token_account.mint,
+            &ctx.accounts.treasury_token_account.key(),
+        )?;
         
-        require!(tokens_to_purchase <= available_tokens, PresaleError::InsufficientTokens);
+        let current_time = Clock::get()?.unix_timestamp;
+        let allowlist_phase = presale.is_allowlist_phase(current_time);
+        if allowlist_phase {
+            let leaf = allowlist::leaf(&ctx.accounts.buyer.key(), allowlist_allocation);
+            require!(
+                allowlist::verify(&presale.allowlist_root, leaf, &allowlist_proof),
+                PresaleError::NotAllowlisted
+            );
+        }
+
+        let user_info = &mut ctx.accounts.user_info;
+        user_info.wallet = ctx.accounts.buyer.key();
+        let (tokens_to_purchase, amount_usd) =
+            presale.fill_order(user_info, amount_usd, min_tokens_out, max_price, current_time)?;
         
         // Process payment based on currency
-        match currency {
+        let amount_paid = match currency {
             PaymentCurrency::USDT => {
+                let amount = math::usd_to_stablecoin(amount_usd, presale.usdt_decimals as u32)
+                    .ok_or(PresaleError::CalculationError)?;
                 let cpi_accounts = Transfer {
                     from: ctx.accounts.buyer_token_account.to_account_info(),
                     to: ctx.accounts.treasury_token_account.to_account_info(),
@@ -121,10 +525,13 @@ pub mod soul_presale {
                 };
                 let cpi_program = ctx.accounts.token_program.to_account_info();
                 let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-                token::transfer(cpi_ctx, amount_usd)?;
+                token::transfer(cpi_ctx, amount)?;
+                amount
             },
             PaymentCurrency::USDC => {
                 // Same as USDT
+                let amount = math::usd_to_stablecoin(amount_usd, presale.usdc_decimals as u32)
+                    .ok_or(PresaleError::CalculationError)?;
                 let cpi_accounts = Transfer {
                     from: ctx.accounts.buyer_token_account.to_account_info(),
                     to: ctx.accounts.treasury_token_account.to_account_info(),
@@ -132,146 +539,325 @@ pub mod soul_presale {
                 };
                 let cpi_program = ctx.accounts.token_program.to_account_info();
                 let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-                token::transfer(cpi_ctx, amount_usd)?;
+                token::transfer(cpi_ctx, amount)?;
+                amount
             },
             PaymentCurrency::SOL => {
-                // Requires integration with Oracle for SOL/USD price
-                // TODO: Add Pyth Oracle integration
+                let price = load_sol_usd_price(&ctx.accounts.sol_price_feed, current_time)?;
+                let lamports = usd_to_lamports(amount_usd, &price)?;
+
+                let cpi_accounts = system_program::Transfer {
+                    from: ctx.accounts.buyer.to_account_info(),
+                    to: ctx.accounts.sol_vault.to_account_info(),
+                };
+                let cpi_program = ctx.accounts.system_program.to_account_info();
+                let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+                system_program::transfer(cpi_ctx, lamports)?;
+                lamports
             },
+        };
+        // Tokens are minted to the buyer as they vest
+        let user_info = &mut ctx.accounts.user_info;
+        user_info.record_contribution(currency, amount_paid)?;
+        if allowlist_phase {
+            user_info.allowlist_purchased = user_info
+                .allowlist_purchased
+                .checked_add(tokens_to_purchase)
+                .ok_or(PresaleError::CalculationError)?;
+            require!(
+                user_info.allowlist_purchased <= allowlist_allocation,
+                PresaleError::AllowlistAllocationExceeded
+            );
         }
-        
-        // Update presale state
-        match presale.stage {
-            PresaleStage::StageOne => presale.stage_one_allocation -= tokens_to_purchase,
-            PresaleStage::StageTwo => presale.stage_two_allocation -= tokens_to_purchase,
-            PresaleStage::StageThree => presale.stage_three_allocation -= tokens_to_purchase,
-            _ => return Err(PresaleError::InvalidStage.into()),
+        // Credit the referrer, commission is paid in the stablecoin of the purchase
+        let mut referral_commission = 0;
+        if let Some(referrer_info) = ctx.accounts.referrer_info.as_mut() {
+            require_keys_neq!(referrer_info.referrer, ctx.accounts.buyer.key(), PresaleError::SelfReferral);
+
+            let (bonus_tokens, commission_usd) = presale.referral.rewards(referrer_info, tokens_to_purchase, amount_usd)?;
+            let bonus_tokens = presale.reserve_bonus(bonus_tokens, current_time)?;
+            let commission = match currency {
+                PaymentCurrency::SOL => 0,
+                _ => u64::try_from(amount_paid as u128 * commission_usd as u128 / amount_usd as u128)
+                    .map_err(|_| PresaleError::CalculationError)?,
+            };
+            let (referrer_owed, presale_owed) = match currency {
+                PaymentCurrency::SOL => (None, None),
+                PaymentCurrency::USDT => (Some(&mut referrer_info.commission_usdt), Some(&mut presale.referral_owed_usdt)),
+                PaymentCurrency::USDC => (Some(&mut referrer_info.commission_usdc), Some(&mut presale.referral_owed_usdc)),
+            };
+            if let (Some(referrer_owed), Some(presale_owed)) = (referrer_owed, presale_owed) {
+                *referrer_owed = referrer_owed.checked_add(commission).ok_or(PresaleError::CalculationError)?;
+                *presale_owed = presale_owed.checked_add(commission).ok_or(PresaleError::CalculationError)?;
+            }
+            let commission_usd = if commission > 0 { commission_usd } else { 0 };
+
+            referrer_info.bonus_tokens = referrer_info
+                .bonus_tokens
+                .checked_add(bonus_tokens)
+                .ok_or(PresaleError::CalculationError)?;
+            referrer_info.commission_usd = referrer_info
+                .commission_usd
+                .checked_add(commission_usd)
+                .ok_or(PresaleError::CalculationError)?;
+            referrer_info.referred_usd = referrer_info
+                .referred_usd
+                .checked_add(amount_usd)
+                .ok_or(PresaleError::CalculationError)?;
+
+            emit!(ReferralCredited {
+                referrer: referrer_info.referrer,
+                buyer: ctx.accounts.buyer.key(),
+                bonus_tokens,
+                commission,
+                commission_usd,
+            });
+            referral_commission = commission;
         }
-        
-        presale.tokens_sold_current_stage += tokens_to_purchase;
-        presale.total_tokens_sold += tokens_to_purchase;
-        
-        // Create or update user info
-        let user_info = &mut ctx.accounts.user_info;
-        user_info.wallet = ctx.accounts.buyer.key();
-        user_info.total_purchased += tokens_to_purchase;
-        
-        // Mint tokens to buyer
-        let cpi_accounts = token::MintTo {
-            mint: ctx.accounts.soul_mint.to_account_info(),
-            to: ctx.accounts.buyer_soul_token_account.to_account_info(),
-            authority: ctx.accounts.presale_state.to_account_info(),
-        };
-        let cpi_program = ctx.accounts.token_program.to_account_info();
+
+        // The rest of the payment is split across the treasury recipients
+        presale.treasury_split.credit(currency, amount_paid - referral_commission)?;
+
+        Ok(())
+    }
+
+    /// Pays a split recipient what it is owed, callable by anyone once the soft cap is reached.
+    /// Payouts are bound by the treasury policy's destinations and allowances.
+    pub fn distribute_split(ctx: Context<DistributeSplit>, index: u8) -> Result<()> {
+        let presale = &mut ctx.accounts.presale_state;
+        require!(presale.soft_cap_reached(), PresaleError::SoftCapNotReached);
+        let clock = Clock::get()?;
+
+        let wallet = presale
+            .treasury_split
+            .recipients
+            .get(index as usize)
+            .ok_or(PresaleError::InvalidTreasurySplit)?
+            .wallet;
+        require_keys_eq!(wallet, ctx.accounts.recipient.key(), PresaleError::InvalidDestination);
+
+        let (usdt_amount, usdc_amount, lamports) = presale.treasury_split.take_payout(
+            index as usize,
+            &mut ctx.accounts.treasury_policy,
+            ctx.accounts.recipient_usdt_account.is_some(),
+            ctx.accounts.recipient_usdc_account.is_some(),
+            clock.unix_timestamp,
+        )?;
+
         let seeds = &[
-            b"presale",
-            &[*ctx.bumps.get("presale_state").unwrap()],
+            b"presale".as_ref(),
+            &[ctx.bumps.presale_state],
         ];
         let signer = &[&seeds[..]];
-        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
-        token::mint_to(cpi_ctx, tokens_to_purchase)?;
 
-        // Emit token purchase event
-        emit!(TokensPurchased {
-            buyer: ctx.accounts.buyer.key(),
-            token_amount: tokens_to_purchase,
-            cost: amount_usd,
-            stage: presale.stage as u8,
+        if let (Some(receiver), true) = (ctx.accounts.recipient_usdt_account.as_ref(), usdt_amount > 0) {
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.usdt_vault.to_account_info(),
+                to: receiver.to_account_info(),
+                authority: ctx.accounts.presale_state.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+            token::transfer(cpi_ctx, usdt_amount)?;
+        }
+
+        if let (Some(receiver), true) = (ctx.accounts.recipient_usdc_account.as_ref(), usdc_amount > 0) {
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.usdc_vault.to_account_info(),
+                to: receiver.to_account_info(),
+                authority: ctx.accounts.presale_state.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+            token::transfer(cpi_ctx, usdc_amount)?;
+        }
+
+        if lamports > 0 {
+            let seeds = &[
+                b"sol_vault".as_ref(),
+                &[ctx.bumps.sol_vault],
+            ];
+            let signer = &[&seeds[..]];
+            let cpi_accounts = system_program::Transfer {
+                from: ctx.accounts.sol_vault.to_account_info(),
+                to: ctx.accounts.recipient.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.system_program.to_account_info();
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+            system_program::transfer(cpi_ctx, lamports)?;
+        }
+
+        emit!(SplitDistributed {
+            recipient: ctx.accounts.recipient.key(),
+            usdt_amount,
+            usdc_amount,
+            lamports,
+            time: clock.unix_timestamp,
         });
 
         Ok(())
     }
 
-    pub fn advance_stage(ctx: Context<UpdatePresale>) -> Result<()> {
+    /// Pays out stablecoin commission and adds bonus SOUL to the referrer's
+    /// pre-sale entitlement, which vests and is claimed like purchased tokens
+    pub fn claim_referral_rewards(ctx: Context<ClaimReferralRewards>) -> Result<()> {
+        require!(
+            ctx.accounts.presale_state.stage == PresaleStage::Ended,
+            PresaleError::InvalidStage
+        );
+
+        let referrer_info = &mut ctx.accounts.referrer_info;
+        let usdt_amount = referrer_info.commission_usdt;
+        let usdc_amount = referrer_info.commission_usdc;
+        let bonus_tokens = referrer_info
+            .bonus_tokens
+            .checked_sub(referrer_info.bonus_tokens_claimed)
+            .ok_or(PresaleError::CalculationError)?;
+        require!(usdt_amount > 0 || usdc_amount > 0 || bonus_tokens > 0, PresaleError::NothingToClaim);
+
+        referrer_info.commission_usdt = 0;
+        referrer_info.commission_usdc = 0;
+        referrer_info.bonus_tokens_claimed = referrer_info.bonus_tokens;
+
+        let user_info = &mut ctx.accounts.user_info;
+        user_info.wallet = ctx.accounts.referrer.key();
+        user_info.total_purchased = user_info
+            .total_purchased
+            .checked_add(bonus_tokens)
+            .ok_or(PresaleError::CalculationError)?;
+
         let presale = &mut ctx.accounts.presale_state;
+        presale.referral_owed_usdt = presale
+            .referral_owed_usdt
+            .checked_sub(usdt_amount)
+            .ok_or(PresaleError::CalculationError)?;
+        presale.referral_owed_usdc = presale
+            .referral_owed_usdc
+            .checked_sub(usdc_amount)
+            .ok_or(PresaleError::CalculationError)?;
+
+        let seeds = &[
+            b"presale".as_ref(),
+            &[ctx.bumps.presale_state],
+        ];
+        let signer = &[&seeds[..]];
+
+        if usdt_amount > 0 {
+            let receiver = ctx
+                .accounts
+                .referrer_usdt_account
+                .as_ref()
+                .ok_or(PresaleError::InvalidPaymentAccount)?;
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.usdt_vault.to_account_info(),
+                to: receiver.to_account_info(),
+                authority: ctx.accounts.presale_state.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+            token::transfer(cpi_ctx, usdt_amount)?;
+        }
+
+        if usdc_amount > 0 {
+            let receiver = ctx
+                .accounts
+                .referrer_usdc_account
+                .as_ref()
+                .ok_or(PresaleError::InvalidPaymentAccount)?;
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.usdc_vault.to_account_info(),
+                to: receiver.to_account_info(),
+                authority: ctx.accounts.presale_state.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+            token::transfer(cpi_ctx, usdc_amount)?;
+        }
+
         let clock = Clock::get()?;
-        
-        require!(clock.unix_timestamp >= presale.current_stage_end, PresaleError::TooEarly);
-        
-        // Emit current stage closing event
-        emit!(StageClosed {
-            stage: presale.stage as u8,
-            time_closed: clock.unix_timestamp,
-            tokens_sold: presale.tokens_sold_current_stage,
-            tokens_left: match presale.stage {
-                PresaleStage::StageOne => presale.stage_one_allocation,
-                PresaleStage::StageTwo => presale.stage_two_allocation,
-                PresaleStage::StageThree => presale.stage_three_allocation,
-                _ => 0,
-            },
+        emit!(ReferralRewardsClaimed {
+            referrer: ctx.accounts.referrer.key(),
+            usdt_amount,
+            usdc_amount,
+            bonus_tokens,
+            time: clock.unix_timestamp,
         });
-        
-        match presale.stage {
-            PresaleStage::StageOne => {
-                let remaining_tokens = presale.stage_one_allocation;
-                presale.stage = PresaleStage::StageTwo;
-                // Transfer unsold tokens
-                presale.stage_two_allocation += remaining_tokens;
-                presale.stage_one_allocation = 0;
 
-                if remaining_tokens > 0 {
-                    emit!(TokensCarriedOver {
-                        from_stage: 1,
-                        to_stage: 2,
-                        amount: remaining_tokens,
-                    });
-                }
+        Ok(())
+    }
 
-                emit!(StageOpened {
-                    stage: 2,
-                    start_time: clock.unix_timestamp,
-                    end_time: clock.unix_timestamp + 30 * 24 * 60 * 60,
-                    token_allocation: presale.stage_two_allocation,
-                    price: presale.base_price_usd * 140 / 100,
-                });
-            },
-            PresaleStage::StageTwo => {
-                let remaining_tokens = presale.stage_two_allocation;
-                presale.stage = PresaleStage::StageThree;
-                presale.stage_three_allocation += remaining_tokens;
-                presale.stage_two_allocation = 0;
+    pub fn claim_presale_tokens(ctx: Context<ClaimPresaleTokens>) -> Result<()> {
+        let presale = &ctx.accounts.presale_state;
+        require!(presale.stage == PresaleStage::Ended, PresaleError::InvalidStage);
 
-                if remaining_tokens > 0 {
-                    emit!(TokensCarriedOver {
-                        from_stage: 2,
-                        to_stage: 3,
-                        amount: remaining_tokens,
-                    });
-                }
+        let clock = Clock::get()?;
+        let amount_to_claim = ctx
+            .accounts
+            .user_info
+            .claim_vested(&presale.vesting, presale.vesting_start, clock.unix_timestamp)?;
+
+        // Mint vested tokens to buyer through the token program, which records
+        // them against the presale supply cap
+        let accounts = crate::accounts::MintPresaleTokens {
+            presale_signer: ctx.accounts.presale_state.key(),
+            state: ctx.accounts.token_state.key(),
+            ledger: ctx.accounts.supply_ledger.key(),
+            mint: ctx.accounts.soul_mint.key(),
+            mint_authority: ctx.accounts.mint_authority.key(),
+            token_account: ctx.accounts.buyer_soul_token_account.key(),
+            token_program: ctx.accounts.token_program.key(),
+        };
+        let ix = Instruction {
+            program_id: crate::ID,
+            accounts: accounts.to_account_metas(None),
+            data: crate::instruction::MintPresaleTokens { amount: amount_to_claim }.data(),
+        };
+        let seeds = &[
+            b"presale".as_ref(),
+            &[ctx.bumps.presale_state],
+        ];
+        invoke_signed(
+            &ix,
+            &[
+                ctx.accounts.presale_state.to_account_info(),
+                ctx.accounts.token_state.to_account_info(),
+                ctx.accounts.supply_ledger.to_account_info(),
+                ctx.accounts.soul_mint.to_account_info(),
+                ctx.accounts.mint_authority.to_account_info(),
+                ctx.accounts.buyer_soul_token_account.to_account_info(),
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.soul_token_program.to_account_info(),
+            ],
+            &[&seeds[..]],
+        )?;
+
+        emit!(TokensClaimed {
+            buyer: ctx.accounts.buyer.key(),
+            amount: amount_to_claim,
+            total_claimed: ctx.accounts.user_info.tokens_claimed,
+            time: clock.unix_timestamp,
+        });
 
-                emit!(StageOpened {
-                    stage: 3,
-                    start_time: clock.unix_timestamp,
-                    end_time: clock.unix_timestamp + 30 * 24 * 60 * 60,
-                    token_allocation: presale.stage_three_allocation,
-                    price: presale.base_price_usd * 196 / 100,
-                });
-            },
-            PresaleStage::StageThree => {
-                presale.stage = PresaleStage::Ended;
-            },
-            _ => return Err(PresaleError::InvalidStage.into()),
-        }
-        
-        presale.current_stage_start = clock.unix_timestamp;
-        presale.current_stage_end = presale.current_stage_start + 30 * 24 * 60 * 60;
-        presale.tokens_sold_current_stage = 0;
-        
         Ok(())
     }
 
+    pub fn advance_stage(ctx: Context<OperatePresale>) -> Result<()> {
+        let clock = Clock::get()?;
+        ctx.accounts.presale_state.advance_stage(clock.unix_timestamp)
+    }
+
     pub fn burn_remaining_tokens(ctx: Context<BurnTokens>) -> Result<()> {
         let presale = &mut ctx.accounts.presale_state;
         require!(presale.stage == PresaleStage::Ended, PresaleError::InvalidStage);
         
-        let remaining_tokens = presale.stage_three_allocation;
+        let remaining_tokens = presale.unsold_tokens;
         require!(remaining_tokens > 0, PresaleError::NoTokensToBurn);
         
         // Burn remaining tokens
         let cpi_accounts = token::Burn {
             mint: ctx.accounts.soul_mint.to_account_info(),
             from: ctx.accounts.burn_token_account.to_account_info(),
-            authority: ctx.accounts.authority.to_account_info(),
+            authority: ctx.accounts.operator.to_account_info(),
         };
         let cpi_program = ctx.accounts.token_program.to_account_info();
         let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
@@ -280,85 +866,711 @@ pub mod soul_presale {
         let clock = Clock::get()?;
         emit!(TokensBurned {
             amount: remaining_tokens,
-            burner: ctx.accounts.authority.key(),
+            burner: ctx.accounts.operator.key(),
             time: clock.unix_timestamp,
         });
 
-        presale.stage_three_allocation = 0;
+        presale.unsold_tokens = 0;
         Ok(())
     }
 
-    pub fn toggle_pause(ctx: Context<UpdatePresale>) -> Result<()> {
+    /// First step of an authority handover, the new authority has to accept it
+    pub fn propose_authority(ctx: Context<ConfigurePresale>, new_authority: Pubkey) -> Result<()> {
         let presale = &mut ctx.accounts.presale_state;
-        presale.is_paused = !presale.is_paused;
+        presale.propose_authority(new_authority);
+
+        emit!(AuthorityTransferProposed {
+            state: presale.key(),
+            current_authority: presale.authority,
+            pending_authority: new_authority,
+        });
         Ok(())
     }
 
-    pub fn withdraw_funds(ctx: Context<WithdrawFunds>, amount: u64) -> Result<()> {
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.treasury_token_account.to_account_info(),
-            to: ctx.accounts.receiver_token_account.to_account_info(),
-            authority: ctx.accounts.authority.to_account_info(),
-        };
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-        token::transfer(cpi_ctx, amount)?;
+    pub fn cancel_authority_transfer(ctx: Context<ConfigurePresale>) -> Result<()> {
+        let presale = &mut ctx.accounts.presale_state;
+        let cancelled_authority = presale.cancel_authority_transfer()?;
 
-        let clock = Clock::get()?;
-        emit!(FundsWithdrawn {
-            receiver: ctx.accounts.receiver_token_account.key(),
-            amount,
-            time: clock.unix_timestamp,
+        emit!(AuthorityTransferCancelled {
+            state: presale.key(),
+            authority: presale.authority,
+            cancelled_authority,
         });
+        Ok(())
+    }
 
+    pub fn accept_authority(ctx: Context<AcceptPresaleAuthority>) -> Result<()> {
+        let presale = &mut ctx.accounts.presale_state;
+        let previous_authority = presale.accept_authority(&ctx.accounts.new_authority.key())?;
+
+        emit!(AuthorityTransferred {
+            state: presale.key(),
+            previous_authority,
+            new_authority: presale.authority,
+            time: Clock::get()?.unix_timestamp,
+        });
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-pub struct InitializePresale<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + 32 + 32 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 8 + 8 + 8 + 8,
+    /// Creates the roles registry with the multisig signer as first admin
+    pub fn initialize_roles(ctx: Context<InitializePresaleRoles>) -> Result<()> {
+        let roles = &mut ctx.accounts.roles;
+        roles.members = Vec::new();
+        let multisig_signer = multisig::multisig_signer();
+        roles.grant(Role::Admin, multisig_signer).map_err(PresaleError::from)?;
+
+        emit!(RoleGranted {
+            registry: roles.key(),
+            role: Role::Admin,
+            member: multisig_signer,
+            admin: ctx.accounts.authority.key(),
+        });
+        Ok(())
+    }
+
+    pub fn grant_role(ctx: Context<ManagePresaleRoles>, role: Role, member: Pubkey) -> Result<()> {
+        let roles = &mut ctx.accounts.roles;
+        roles.grant(role, member).map_err(PresaleError::from)?;
+
+        emit!(RoleGranted {
+            registry: roles.key(),
+            role,
+            member,
+            admin: ctx.accounts.admin.key(),
+        });
+        Ok(())
+    }
+
+    pub fn revoke_role(ctx: Context<ManagePresaleRoles>, role: Role, member: Pubkey) -> Result<()> {
+        let roles = &mut ctx.accounts.roles;
+        roles.revoke(role, &member).map_err(PresaleError::from)?;
+
+        emit!(RoleRevoked {
+            registry: roles.key(),
+            role,
+            member,
+            admin: ctx.accounts.admin.key(),
+        });
+        Ok(())
+    }
+
+    pub fn toggle_pause(ctx: Context<PausePresale>) -> Result<()> {
+        let presale = &mut ctx.accounts.presale_state;
+        presale.is_paused = !presale.is_paused;
+        Ok(())
+    }
+
+    pub fn claim_refund(ctx: Context<ClaimRefund>) -> Result<()> {
+        require!(
+            ctx.accounts.presale_state.stage == PresaleStage::Refunding,
+            PresaleError::InvalidStage
+        );
+
+        let (usdt_amount, usdc_amount, lamports) = ctx.accounts.user_info.take_refund()?;
+
+        let seeds = &[
+            b"presale".as_ref(),
+            &[ctx.bumps.presale_state],
+        ];
+        let signer = &[&seeds[..]];
+
+        if usdt_amount > 0 {
+            let receiver = ctx
+                .accounts
+                .buyer_usdt_account
+                .as_ref()
+                .ok_or(PresaleError::InvalidPaymentAccount)?;
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.usdt_vault.to_account_info(),
+                to: receiver.to_account_info(),
+                authority: ctx.accounts.presale_state.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+            token::transfer(cpi_ctx, usdt_amount)?;
+        }
+
+        if usdc_amount > 0 {
+            let receiver = ctx
+                .accounts
+                .buyer_usdc_account
+                .as_ref()
+                .ok_or(PresaleError::InvalidPaymentAccount)?;
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.usdc_vault.to_account_info(),
+                to: receiver.to_account_info(),
+                authority: ctx.accounts.presale_state.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+            token::transfer(cpi_ctx, usdc_amount)?;
+        }
+
+        if lamports > 0 {
+            let vault_seeds = &[
+                b"sol_vault".as_ref(),
+                &[ctx.bumps.sol_vault],
+            ];
+            let vault_signer = &[&vault_seeds[..]];
+            let cpi_accounts = system_program::Transfer {
+                from: ctx.accounts.sol_vault.to_account_info(),
+                to: ctx.accounts.buyer.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.system_program.to_account_info();
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, vault_signer);
+            system_program::transfer(cpi_ctx, lamports)?;
+        }
+
+        let clock = Clock::get()?;
+        emit!(RefundClaimed {
+            buyer: ctx.accounts.buyer.key(),
+            usdt_amount,
+            usdc_amount,
+            lamports,
+            time: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the treasury destinations and the per-currency limit of each withdrawal window.
+    /// Windows already open keep what was withdrawn in them.
+    pub fn set_treasury_policy(
+        ctx: Context<SetTreasuryPolicy>,
+        destinations: Vec<Pubkey>,
+        usdt_period_limit: u64,
+        usdc_period_limit: u64,
+        sol_period_limit: u64,
+    ) -> Result<()> {
+        require!(
+            !destinations.is_empty() && destinations.len() <= MAX_TREASURY_DESTINATIONS,
+            PresaleError::InvalidTreasuryPolicy
+        );
+
+        let policy = &mut ctx.accounts.treasury_policy;
+        policy.destinations = destinations;
+        policy.usdt.period_limit = usdt_period_limit;
+        policy.usdc.period_limit = usdc_period_limit;
+        policy.sol.period_limit = sol_period_limit;
+
+        emit!(TreasuryPolicyUpdated {
+            destinations: policy.destinations.clone(),
+            usdt_period_limit,
+            usdc_period_limit,
+            sol_period_limit,
+        });
+        Ok(())
+    }
+
+    pub fn withdraw_funds(ctx: Context<WithdrawFunds>, amount: u64) -> Result<()> {
+        let presale = &ctx.accounts.presale_state;
+        require!(presale.soft_cap_reached(), PresaleError::SoftCapNotReached);
+        let clock = Clock::get()?;
+
+        let currency = if ctx.accounts.treasury_token_account.key() == presale.usdt_vault {
+            PaymentCurrency::USDT
+        } else {
+            PaymentCurrency::USDC
+        };
+        let remaining_allowance = ctx
+            .accounts
+            .treasury_policy
+            .allowance(currency)
+            .withdraw(amount, clock.unix_timestamp)?;
+
+        // Referral commission and split shares owed from this vault stay in it
+        let treasury = &ctx.accounts.treasury_token_account;
+        let referral_owed = match currency {
+            PaymentCurrency::USDT => presale.referral_owed_usdt,
+            _ => presale.referral_owed_usdc,
+        };
+        let owed = referral_owed
+            .checked_add(presale.treasury_split.owed(currency))
+            .ok_or(PresaleError::CalculationError)?;
+        let remaining = treasury.amount.checked_sub(amount).ok_or(PresaleError::InsufficientFunds)?;
+        require!(remaining >= owed, PresaleError::InsufficientFunds);
+
+        // Vaults are owned by the presale PDA
+        let seeds = &[
+            b"presale".as_ref(),
+            &[ctx.bumps.presale_state],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.treasury_token_account.to_account_info(),
+            to: ctx.accounts.receiver_token_account.to_account_info(),
+            authority: ctx.accounts.presale_state.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, amount)?;
+
+        emit!(FundsWithdrawn {
+            receiver: ctx.accounts.receiver_token_account.key(),
+            currency,
+            amount,
+            remaining_allowance,
+            time: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn withdraw_sol(ctx: Context<WithdrawSol>, lamports: u64) -> Result<()> {
+        let presale = &ctx.accounts.presale_state;
+        require!(presale.soft_cap_reached(), PresaleError::SoftCapNotReached);
+        let clock = Clock::get()?;
+
+        // Split shares owed in SOL stay in the vault
+        let remaining = ctx
+            .accounts
+            .sol_vault
+            .lamports()
+            .checked_sub(lamports)
+            .ok_or(PresaleError::InsufficientFunds)?;
+        require!(
+            remaining >= presale.treasury_split.owed(PaymentCurrency::SOL),
+            PresaleError::InsufficientFunds
+        );
+
+        let remaining_allowance = ctx
+            .accounts
+            .treasury_policy
+            .allowance(PaymentCurrency::SOL)
+            .withdraw(lamports, clock.unix_timestamp)?;
+
+        let seeds = &[
+            b"sol_vault".as_ref(),
+            &[ctx.bumps.sol_vault],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = system_program::Transfer {
+            from: ctx.accounts.sol_vault.to_account_info(),
+            to: ctx.accounts.treasury_wallet.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.system_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        system_program::transfer(cpi_ctx, lamports)?;
+
+        emit!(FundsWithdrawn {
+            receiver: ctx.accounts.treasury_wallet.key(),
+            currency: PaymentCurrency::SOL,
+            amount: lamports,
+            remaining_allowance,
+            time: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct InitializePresale<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 32 + 32 + 32 + 32 + 32 + 32 + 32 + 32 + 32 + 1 + 1 + 1 + 1 + 1 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 8
+            + 32 + 8 + PurchaseLimits::LEN + 8 + 8 + ReferralConfig::LEN + 8 + 8 + TreasurySplit::LEN + 4 + StageConfig::LEN * MAX_STAGES + VestingTerms::LEN + 8,
+        seeds = [b"presale"],
+        bump
+    )]
+    pub presale_state: Account<'info, PresaleState>,
+    
+    pub soul_mint: Account<'info, Mint>,
+    pub usdt_mint: Account<'info, Mint>,
+    pub usdc_mint: Account<'info, Mint>,
+    
+    #[account(
+        init,
+        payer = authority,
+        token::mint = usdt_mint,
+        token::authority = presale_state,
+        seeds = [b"vault", usdt_mint.key().as_ref()],
+        bump
+    )]
+    pub usdt_vault: Account<'info, TokenAccount>,
+    
+    #[account(
+        init,
+        payer = authority,
+        token::mint = usdc_mint,
+        token::authority = presale_state,
+        seeds = [b"vault", usdc_mint.key().as_ref()],
+        bump
+    )]
+    pub usdc_vault: Account<'info, TokenAccount>,
+    
+    #[account(mut)]
+    pub treasury_wallet: SystemAccount<'info>,
+    /// CHECK: Parsed as a Pyth SOL/USD price account on every SOL purchase
+    pub sol_price_feed: UncheckedAccount<'info>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePresale<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"presale"],
+        bump,
+        has_one = authority,
+    )]
+    pub presale_state: Account<'info, PresaleState>,
+}
+
+/// Configuration changes, only executed through the timelock queue
+#[derive(Accounts)]
+pub struct ConfigurePresale<'info> {
+    #[account(
+        seeds = [b"timelock_signer"],
+        bump,
+        seeds::program = crate::ID,
+    )]
+    pub timelock_signer: Signer<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"presale"],
+        bump,
+    )]
+    pub presale_state: Account<'info, PresaleState>,
+}
+
+#[derive(Accounts)]
+pub struct OperatePresale<'info> {
+    pub operator: Signer<'info>,
+    
+    #[account(
+        seeds = [b"roles"],
+        bump,
+        constraint = roles.has_role(Role::Operator, &operator.key()) @ PresaleError::MissingRole,
+    )]
+    pub roles: Account<'info, RoleRegistry>,
+    
+    #[account(
+        mut,
+        seeds = [b"presale"],
+        bump,
+    )]
+    pub presale_state: Account<'info, PresaleState>,
+}
+
+#[derive(Accounts)]
+pub struct PausePresale<'info> {
+    pub pauser: Signer<'info>,
+    
+    #[account(
+        seeds = [b"roles"],
+        bump,
+        constraint = roles.has_role(Role::Pauser, &pauser.key()) @ PresaleError::MissingRole,
+    )]
+    pub roles: Account<'info, RoleRegistry>,
+    
+    #[account(
+        mut,
+        seeds = [b"presale"],
+        bump,
+    )]
+    pub presale_state: Account<'info, PresaleState>,
+}
+
+#[derive(Accounts)]
+pub struct InitializePresaleRoles<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        seeds = [b"presale"],
+        bump,
+        has_one = authority,
+    )]
+    pub presale_state: Account<'info, PresaleState>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + RoleRegistry::LEN,
+        seeds = [b"roles"],
+        bump
+    )]
+    pub roles: Account<'info, RoleRegistry>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ManagePresaleRoles<'info> {
+    pub admin: Signer<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"roles"],
+        bump,
+        constraint = roles.has_role(Role::Admin, &admin.key()) @ PresaleError::MissingRole,
+    )]
+    pub roles: Account<'info, RoleRegistry>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptPresaleAuthority<'info> {
+    pub new_authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"presale"],
+        bump,
+    )]
+    pub presale_state: Account<'info, PresaleState>,
+}
+
+#[derive(Accounts)]
+pub struct PurchaseTokens<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"presale"],
+        bump
+    )]
+    pub presale_state: Account<'info, PresaleState>,
+    
+    #[account(
+        mut,
+        constraint = buyer_token_account.owner == buyer.key() @ PresaleError::InvalidPaymentAccount,
+    )]
+    pub buyer_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = presale_state.is_vault(&treasury_token_account.key()) @ PresaleError::InvalidTreasury,
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        seeds = [b"sol_vault"],
+        bump
+    )]
+    pub sol_vault: SystemAccount<'info>,
+    
+    /// CHECK: Must be the SOL/USD price account stored at initialization
+    #[account(address = presale_state.sol_price_feed @ PresaleError::InvalidPriceFeed)]
+    pub sol_price_feed: UncheckedAccount<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + UserPresaleInfo::LEN,
+        seeds = [b"user_info", buyer.key().as_ref()],
+        bump
+    )]
+    pub user_info: Account<'info, UserPresaleInfo>,
+    
+    /// Set when the purchase was referred
+    #[account(mut)]
+    pub referrer_info: Option<Account<'info, ReferrerInfo>>,
+    
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimPresaleTokens<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    
+    #[account(
+        seeds = [b"presale"],
+        bump
+    )]
+    pub presale_state: Account<'info, PresaleState>,
+    
+    #[account(
+        mut,
+        address = presale_state.soul_mint,
+    )]
+    pub soul_mint: Account<'info, Mint>,
+    
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        associated_token::mint = soul_mint,
+        associated_token::authority = buyer
+    )]
+    pub buyer_soul_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_info", buyer.key().as_ref()],
+        bump
+    )]
+    pub user_info: Account<'info, UserPresaleInfo>,
+    
+    pub token_state: Account<'info, TokenState>,
+    
+    #[account(mut)]
+    pub supply_ledger: Account<'info, SupplyLedger>,
+    
+    /// CHECK: Validated by the token program
+    pub mint_authority: UncheckedAccount<'info>,
+    
+    pub soul_token_program: Program<'info, crate::program::SoulToken>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BurnTokens<'info> {
+    #[account(mut)]
+    pub operator: Signer<'info>,
+    
+    #[account(
+        seeds = [b"roles"],
+        bump,
+        constraint = roles.has_role(Role::Operator, &operator.key()) @ PresaleError::MissingRole,
+    )]
+    pub roles: Account<'info, RoleRegistry>,
+    
+    #[account(
+        mut,
+        seeds = [b"presale"],
+        bump,
+    )]
+    pub presale_state: Account<'info, PresaleState>,
+    
+    #[account(mut)]
+    pub soul_mint: Account<'info, Mint>,
+    
+    #[account(mut)]
+    pub burn_token_account: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFunds<'info> {
+    #[account(mut)]
+    pub treasurer: Signer<'info>,
+    
+    #[account(
+        seeds = [b"roles"],
+        bump,
+        constraint = roles.has_role(Role::Treasurer, &treasurer.key()) @ PresaleError::MissingRole,
+    )]
+    pub roles: Account<'info, RoleRegistry>,
+    
+    #[account(
+        mut,
         seeds = [b"presale"],
-        bump
+        bump,
     )]
     pub presale_state: Account<'info, PresaleState>,
     
-    pub soul_mint: Account<'info, Mint>,
-    pub usdt_mint: Account<'info, Mint>,
-    pub usdc_mint: Account<'info, Mint>,
-    #[account(mut)]
-    pub treasury_wallet: SystemAccount<'info>,
+    #[account(
+        mut,
+        constraint = presale_state.is_vault(&treasury_token_account.key()) @ PresaleError::InvalidTreasury,
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        seeds = [b"treasury_policy"],
+        bump
+    )]
+    pub treasury_policy: Account<'info, TreasuryPolicy>,
+    
+    /// Only allowlisted treasury wallets can receive withdrawals
+    #[account(
+        mut,
+        constraint = treasury_policy.is_destination(&receiver_token_account.owner) @ PresaleError::InvalidDestination,
+    )]
+    pub receiver_token_account: Account<'info, TokenAccount>,
     
-    pub system_program: Program<'info, System>,
     pub token_program: Program<'info, Token>,
-    pub rent: Sysvar<'info, Rent>,
 }
 
 #[derive(Accounts)]
-pub struct UpdatePresale<'info> {
+pub struct WithdrawSol<'info> {
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub treasurer: Signer<'info>,
+    
+    #[account(
+        seeds = [b"roles"],
+        bump,
+        constraint = roles.has_role(Role::Treasurer, &treasurer.key()) @ PresaleError::MissingRole,
+    )]
+    pub roles: Account<'info, RoleRegistry>,
     
     #[account(
-        mut,
         seeds = [b"presale"],
         bump,
-        has_one = authority,
+        has_one = treasury_wallet,
     )]
     pub presale_state: Account<'info, PresaleState>,
+    
+    #[account(
+        mut,
+        seeds = [b"treasury_policy"],
+        bump
+    )]
+    pub treasury_policy: Account<'info, TreasuryPolicy>,
+    
+    #[account(
+        mut,
+        seeds = [b"sol_vault"],
+        bump
+    )]
+    pub sol_vault: SystemAccount<'info>,
+    
+    #[account(
+        mut,
+        constraint = treasury_policy.is_destination(&treasury_wallet.key()) @ PresaleError::InvalidDestination,
+    )]
+    pub treasury_wallet: SystemAccount<'info>,
+    
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct PurchaseTokens<'info> {
+pub struct SetTreasuryPolicy<'info> {
     #[account(mut)]
-    pub buyer: Signer<'info>,
+    pub payer: Signer<'info>,
+    
+    #[account(
+        seeds = [b"timelock_signer"],
+        bump,
+        seeds::program = crate::ID,
+    )]
+    pub timelock_signer: Signer<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + TreasuryPolicy::LEN,
+        seeds = [b"treasury_policy"],
+        bump
+    )]
+    pub treasury_policy: Account<'info, TreasuryPolicy>,
     
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DistributeSplit<'info> {
     #[account(
         mut,
         seeds = [b"presale"],
@@ -366,101 +1578,470 @@ pub struct PurchaseTokens<'info> {
     )]
     pub presale_state: Account<'info, PresaleState>,
     
+    /// Checked against the split table entry and the treasury policy
     #[account(mut)]
-    pub soul_mint: Account<'info, Mint>,
+    pub recipient: SystemAccount<'info>,
     
     #[account(
-        init_if_needed,
-        payer = buyer,
-        associated_token::mint = soul_mint,
-        associated_token::authority = buyer
+        mut,
+        seeds = [b"treasury_policy"],
+        bump
     )]
-    pub buyer_soul_token_account: Account<'info, TokenAccount>,
+    pub treasury_policy: Account<'info, TreasuryPolicy>,
     
-    #[account(mut)]
-    pub buyer_token_account: Account<'info, TokenAccount>,
+    #[account(mut, address = presale_state.usdt_vault)]
+    pub usdt_vault: Account<'info, TokenAccount>,
     
-    #[account(mut)]
-    pub treasury_token_account: Account<'info, TokenAccount>,
+    #[account(mut, address = presale_state.usdc_vault)]
+    pub usdc_vault: Account<'info, TokenAccount>,
     
     #[account(
-        init_if_needed,
-        payer = buyer,
-        space = 8 + 32 + 8 + 8 + 8,
-        seeds = [b"user_info", buyer.key().as_ref()],
+        mut,
+        seeds = [b"sol_vault"],
         bump
     )]
-    pub user_info: Account<'info, UserPresaleInfo>,
+    pub sol_vault: SystemAccount<'info>,
+    
+    #[account(
+        mut,
+        token::mint = presale_state.usdt_mint,
+        token::authority = recipient,
+    )]
+    pub recipient_usdt_account: Option<Account<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        token::mint = presale_state.usdc_mint,
+        token::authority = recipient,
+    )]
+    pub recipient_usdc_account: Option<Account<'info, TokenAccount>>,
     
     pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
 }
 
 #[derive(Accounts)]
-pub struct BurnTokens<'info> {
+pub struct RegisterReferrer<'info> {
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub referrer: Signer<'info>,
+    
+    #[account(
+        init,
+        payer = referrer,
+        space = 8 + ReferrerInfo::LEN,
+        seeds = [b"referrer", referrer.key().as_ref()],
+        bump
+    )]
+    pub referrer_info: Account<'info, ReferrerInfo>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimReferralRewards<'info> {
+    #[account(mut)]
+    pub referrer: Signer<'info>,
     
     #[account(
         mut,
         seeds = [b"presale"],
-        bump,
-        has_one = authority,
+        bump
     )]
     pub presale_state: Account<'info, PresaleState>,
     
-    #[account(mut)]
-    pub soul_mint: Account<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [b"referrer", referrer.key().as_ref()],
+        bump,
+        has_one = referrer,
+    )]
+    pub referrer_info: Account<'info, ReferrerInfo>,
     
-    #[account(mut)]
-    pub burn_token_account: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = referrer,
+        space = 8 + UserPresaleInfo::LEN,
+        seeds = [b"user_info", referrer.key().as_ref()],
+        bump
+    )]
+    pub user_info: Account<'info, UserPresaleInfo>,
+    
+    #[account(mut, address = presale_state.usdt_vault)]
+    pub usdt_vault: Account<'info, TokenAccount>,
+    
+    #[account(mut, address = presale_state.usdc_vault)]
+    pub usdc_vault: Account<'info, TokenAccount>,
+    
+    /// Required when USDT commission is owed
+    #[account(mut, token::mint = presale_state.usdt_mint)]
+    pub referrer_usdt_account: Option<Account<'info, TokenAccount>>,
+    
+    /// Required when USDC commission is owed
+    #[account(mut, token::mint = presale_state.usdc_mint)]
+    pub referrer_usdc_account: Option<Account<'info, TokenAccount>>,
     
     pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct WithdrawFunds<'info> {
+pub struct ClaimRefund<'info> {
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub buyer: Signer<'info>,
     
     #[account(
-        mut,
         seeds = [b"presale"],
-        bump,
-        has_one = authority,
+        bump
     )]
     pub presale_state: Account<'info, PresaleState>,
     
-    #[account(mut)]
-    pub treasury_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"user_info", buyer.key().as_ref()],
+        bump
+    )]
+    pub user_info: Account<'info, UserPresaleInfo>,
     
-    #[account(mut)]
-    pub receiver_token_account: Account<'info, TokenAccount>,
+    #[account(mut, address = presale_state.usdt_vault)]
+    pub usdt_vault: Account<'info, TokenAccount>,
+    
+    #[account(mut, address = presale_state.usdc_vault)]
+    pub usdc_vault: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        seeds = [b"sol_vault"],
+        bump
+    )]
+    pub sol_vault: SystemAccount<'info>,
+    
+    /// Required when the buyer paid in USDT
+    #[account(mut, token::mint = presale_state.usdt_mint)]
+    pub buyer_usdt_account: Option<Account<'info, TokenAccount>>,
+    
+    /// Required when the buyer paid in USDC
+    #[account(mut, token::mint = presale_state.usdc_mint)]
+    pub buyer_usdc_account: Option<Account<'info, TokenAccount>>,
     
     pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
 }
 
 #[account]
 pub struct PresaleState {
     pub authority: Pubkey,
+    pub pending_authority: Pubkey, // Default when no handover is pending
     pub soul_mint: Pubkey,
     pub usdt_mint: Pubkey,
     pub usdc_mint: Pubkey,
     pub treasury_wallet: Pubkey,
+    pub sol_price_feed: Pubkey,
+    pub usdt_vault: Pubkey, // Owned by the presale PDA
+    pub usdc_vault: Pubkey,
+    pub soul_decimals: u8,
+    pub usdt_decimals: u8,
+    pub usdc_decimals: u8,
     pub stage: PresaleStage,
-    pub current_stage_start: i64,
-    pub current_stage_end: i64,
-    pub tokens_per_stage: u64,
+    pub current_stage: u8, // Index into `stages`
+    pub stage_tokens_remaining: u64, // Allocation plus carry-over, minus sales
     pub tokens_sold_current_stage: u64,
     pub total_tokens_sold: u64,
-    pub base_price_usd: u64,  // In USDT cents ($0.005 = 50)
+    pub bonus_tokens_current_stage: u64, // Referral bonuses taken out of the stage
+    pub total_bonus_tokens: u64,
+    pub unsold_tokens: u64, // Not carried over, waiting to be burned
+    pub allowlist_root: [u8; 32], // Zero when there is no allowlist phase
+    pub allowlist_end_time: i64,
+    pub limits: PurchaseLimits,
+    pub soft_cap_usd: u64, // In micro-USD
+    pub total_raised_usd: u64,
+    pub referral: ReferralConfig,
+    pub referral_owed_usdt: u64, // Unclaimed commission held in the vaults
+    pub referral_owed_usdc: u64,
+    pub treasury_split: TreasurySplit,
     pub is_paused: bool,
-    pub min_purchase_amount: u64, // In USDT cents ($50 = 5000)
-    pub stage_one_allocation: u64,
-    pub stage_two_allocation: u64,
-    pub stage_three_allocation: u64,
+    pub min_purchase_amount: u64, // In micro-USD ($50 = 50_000_000)
+    pub stages: Vec<StageConfig>,
+    pub vesting: VestingTerms,
+    pub vesting_start: i64, // Set when the presale ends
+}
+
+impl PresaleState {
+    /// Starts a two-step handover, replacing any pending proposal
+    pub fn propose_authority(&mut self, new_authority: Pubkey) {
+        authority::propose(&mut self.pending_authority, new_authority);
+    }
+
+    /// Drops the pending handover and returns the key that was proposed
+    pub fn cancel_authority_transfer(&mut self) -> Result<Pubkey> {
+        Ok(authority::cancel(&mut self.pending_authority).map_err(PresaleError::from)?)
+    }
+
+    /// Completes the handover when signed by the proposed key, returns the previous authority
+    pub fn accept_authority(&mut self, signer: &Pubkey) -> Result<Pubkey> {
+        Ok(authority::accept(&mut self.authority, &mut self.pending_authority, signer).map_err(PresaleError::from)?)
+    }
+
+    pub fn soft_cap_reached(&self) -> bool {
+        self.total_raised_usd >= self.soft_cap_usd
+    }
+
+    pub fn is_allowlist_phase(&self, current_time: i64) -> bool {
+        self.allowlist_root != [0; 32] && current_time < self.allowlist_end_time
+    }
+
+    pub fn is_vault(&self, token_account: &Pubkey) -> bool {
+        *token_account == self.usdt_vault || *token_account == self.usdc_vault
+    }
+
+    /// Stablecoin payments must use the mint of `currency` and pay into its vault
+    pub fn validate_payment(&self, currency: PaymentCurrency, payment_mint: &Pubkey, treasury: &Pubkey) -> Result<()> {
+        let (mint, vault) = match currency {
+            PaymentCurrency::SOL => return Ok(()),
+            PaymentCurrency::USDT => (self.usdt_mint, self.usdt_vault),
+            PaymentCurrency::USDC => (self.usdc_mint, self.usdc_vault),
+        };
+        require_keys_eq!(*payment_mint, mint, PresaleError::InvalidPaymentMint);
+        require_keys_eq!(*treasury, vault, PresaleError::InvalidTreasury);
+        Ok(())
+    }
+
+    /// Fills an order of `amount_usd` stage by stage and records the tokens on
+    /// `user_info`. A sold-out stage is closed and the rest of the order fills
+    /// in the next one while it is open and within `max_price`, whatever cannot
+    /// be filled is not charged. Returns the tokens bought and the micro-USD
+    /// charged for them.
+    pub fn fill_order(
+        &mut self,
+        user_info: &mut UserPresaleInfo,
+        amount_usd: u64,
+        min_tokens_out: u64,
+        max_price: u64,
+        current_time: i64,
+    ) -> Result<(u64, u64)> {
+        require!(!self.is_paused, PresaleError::PresalePaused);
+        require!(amount_usd >= self.min_purchase_amount, PresaleError::BelowMinimum);
+        let limits = self.limits;
+        require!(
+            limits.max_ticket_usd == 0 || amount_usd <= limits.max_ticket_usd,
+            PresaleError::TicketTooLarge
+        );
+
+        // Price and sale window of the current stage
+        require!(self.stage == PresaleStage::Active, PresaleError::InvalidStage);
+        let stage = self.current_stage_config()?;
+        require!(current_time >= stage.start_time, PresaleError::TooEarly);
+        require!(current_time < stage.end_time, PresaleError::StageEnded);
+
+        // The stage may have advanced since the buyer signed
+        require!(stage.price <= max_price, PresaleError::SlippageExceeded);
+
+        let soul_decimals = self.soul_decimals as u32;
+        let mut unfilled_usd = amount_usd;
+        let mut tokens_bought: u64 = 0;
+        while unfilled_usd > 0 && self.stage == PresaleStage::Active {
+            let stage = self.current_stage_config()?;
+            if current_time < stage.start_time || current_time >= stage.end_time || stage.price > max_price {
+                break;
+            }
+
+            let tokens = math::tokens_for_usd(unfilled_usd, stage.price, soul_decimals)
+                .ok_or(PresaleError::CalculationError)?;
+            let (tokens, cost) = if tokens < self.stage_tokens_remaining {
+                (tokens, unfilled_usd)
+            } else {
+                let tokens = self.stage_tokens_remaining;
+                let cost = math::usd_for_tokens(tokens, stage.price, soul_decimals)
+                    .ok_or(PresaleError::CalculationError)?;
+                (tokens, cost)
+            };
+            if tokens == 0 {
+                break;
+            }
+
+            let stage_purchased = user_info.record_stage_purchase(self.current_stage, tokens)?;
+            require!(
+                limits.max_tokens_per_wallet_per_stage == 0
+                    || stage_purchased <= limits.max_tokens_per_wallet_per_stage,
+                PresaleError::WalletStageCapExceeded
+            );
+
+            self.stage_tokens_remaining = self
+                .stage_tokens_remaining
+                .checked_sub(tokens)
+                .ok_or(PresaleError::CalculationError)?;
+            self.tokens_sold_current_stage = self
+                .tokens_sold_current_stage
+                .checked_add(tokens)
+                .ok_or(PresaleError::CalculationError)?;
+            self.total_tokens_sold = self
+                .total_tokens_sold
+                .checked_add(tokens)
+                .ok_or(PresaleError::CalculationError)?;
+            tokens_bought = tokens_bought
+                .checked_add(tokens)
+                .ok_or(PresaleError::CalculationError)?;
+            unfilled_usd = unfilled_usd
+                .checked_sub(cost)
+                .ok_or(PresaleError::CalculationError)?;
+            // Booked before a sell-out closes the stage, the last fill can reach the soft cap
+            self.total_raised_usd = self
+                .total_raised_usd
+                .checked_add(cost)
+                .ok_or(PresaleError::CalculationError)?;
+
+            emit!(TokensPurchased {
+                buyer: user_info.wallet,
+                token_amount: tokens,
+                cost,
+                stage: self.current_stage + 1,
+            });
+
+            if self.stage_tokens_remaining == 0 {
+                self.close_sold_out_stage(current_time)?;
+            }
+        }
+
+        require!(tokens_bought > 0, PresaleError::InsufficientTokens);
+        require!(tokens_bought >= min_tokens_out, PresaleError::SlippageExceeded);
+        // A partial fill is charged less than requested, it still has to meet the minimum
+        let filled_usd = amount_usd - unfilled_usd;
+        require!(filled_usd >= self.min_purchase_amount, PresaleError::BelowMinimum);
+
+        user_info.total_purchased = user_info
+            .total_purchased
+            .checked_add(tokens_bought)
+            .ok_or(PresaleError::CalculationError)?;
+        require!(
+            limits.max_tokens_per_wallet == 0 || user_info.total_purchased <= limits.max_tokens_per_wallet,
+            PresaleError::WalletCapExceeded
+        );
+
+        Ok((tokens_bought, filled_usd))
+    }
+
+    /// Takes a referral bonus out of the current stage so bonuses stay within
+    /// the pre-sale allocation. Returns the bonus granted, which is cut short
+    /// once the sale is sold out.
+    pub fn reserve_bonus(&mut self, bonus_tokens: u64, current_time: i64) -> Result<u64> {
+        if self.stage != PresaleStage::Active {
+            return Ok(0);
+        }
+
+        let bonus_tokens = bonus_tokens.min(self.stage_tokens_remaining);
+        self.stage_tokens_remaining -= bonus_tokens;
+        self.bonus_tokens_current_stage = self
+            .bonus_tokens_current_stage
+            .checked_add(bonus_tokens)
+            .ok_or(PresaleError::CalculationError)?;
+        self.total_bonus_tokens = self
+            .total_bonus_tokens
+            .checked_add(bonus_tokens)
+            .ok_or(PresaleError::CalculationError)?;
+        if bonus_tokens > 0 && self.stage_tokens_remaining == 0 {
+            self.close_sold_out_stage(current_time)?;
+        }
+        Ok(bonus_tokens)
+    }
+
+    pub fn current_stage_config(&self) -> Result<StageConfig> {
+        self.stages
+            .get(self.current_stage as usize)
+            .copied()
+            .ok_or(error!(PresaleError::InvalidStage))
+    }
+
+    /// Closes the current stage once its end time has passed. The next stage
+    /// keeps its scheduled start time.
+    pub fn advance_stage(&mut self, current_time: i64) -> Result<()> {
+        require!(self.stage == PresaleStage::Active, PresaleError::InvalidStage);
+        let stage = self.current_stage_config()?;
+        require!(current_time >= stage.end_time, PresaleError::TooEarly);
+
+        self.close_stage(current_time)
+    }
+
+    /// Closes a stage that sold out before its end time, the next stage opens right away
+    fn close_sold_out_stage(&mut self, current_time: i64) -> Result<()> {
+        if let Some(next_stage) = self.stages.get_mut(self.current_stage as usize + 1) {
+            next_stage.start_time = next_stage.start_time.min(current_time);
+        }
+        self.close_stage(current_time)
+    }
+
+    /// Closes the current stage and opens the next one, or ends the presale
+    /// after the last stage
+    pub fn close_stage(&mut self, current_time: i64) -> Result<()> {
+        let stage = self.current_stage_config()?;
+
+        // Emit current stage closing event
+        let stage_number = self.current_stage + 1;
+        let remaining_tokens = self.stage_tokens_remaining;
+        emit!(StageClosed {
+            stage: stage_number,
+            time_closed: current_time,
+            tokens_sold: self.tokens_sold_current_stage,
+            bonus_tokens: self.bonus_tokens_current_stage,
+            tokens_left: remaining_tokens,
+        });
+
+        self.stage_tokens_remaining = 0;
+        self.tokens_sold_current_stage = 0;
+        self.bonus_tokens_current_stage = 0;
+
+        let next_stage = match self.stages.get(stage_number as usize) {
+            Some(next_stage) => *next_stage,
+            None => {
+                if self.soft_cap_reached() {
+                    self.stage = PresaleStage::Ended;
+                    self.vesting_start = current_time;
+                } else {
+                    self.stage = PresaleStage::Refunding;
+                }
+                self.unsold_tokens = self
+                    .unsold_tokens
+                    .checked_add(remaining_tokens)
+                    .ok_or(PresaleError::CalculationError)?;
+                return Ok(());
+            }
+        };
+
+        // Handle unsold tokens per the closing stage's policy
+        let mut token_allocation = next_stage.allocation;
+        match stage.carry_over {
+            CarryOver::NextStage => {
+                token_allocation = token_allocation
+                    .checked_add(remaining_tokens)
+                    .ok_or(PresaleError::CalculationError)?;
+
+                if remaining_tokens > 0 {
+                    emit!(TokensCarriedOver {
+                        from_stage: stage_number,
+                        to_stage: stage_number + 1,
+                        amount: remaining_tokens,
+                    });
+                }
+            },
+            CarryOver::Burn => {
+                self.unsold_tokens = self
+                    .unsold_tokens
+                    .checked_add(remaining_tokens)
+                    .ok_or(PresaleError::CalculationError)?;
+            },
+        }
+
+        self.current_stage = stage_number;
+        self.stage_tokens_remaining = token_allocation;
+
+        emit!(StageOpened {
+            stage: stage_number + 1,
+            start_time: next_stage.start_time,
+            end_time: next_stage.end_time,
+            token_allocation,
+            price: next_stage.price,
+        });
+
+        Ok(())
+    }
 }
 
 #[account]
@@ -469,6 +2050,79 @@ pub struct UserPresaleInfo {
     pub total_purchased: u64,
     pub tokens_claimed: u64,
     pub last_claim_time: i64,
+    pub allowlist_purchased: u64, // Counted against the allowlist allocation
+    pub current_stage: u8, // Stage of `current_stage_purchased`
+    pub current_stage_purchased: u64,
+    pub contributed_usdt: u64, // Refunded if the soft cap is missed
+    pub contributed_usdc: u64,
+    pub contributed_lamports: u64,
+}
+
+impl UserPresaleInfo {
+    pub const LEN: usize = 32 + 8 + 8 + 8 + 8 + 1 + 8 + 8 + 8 + 8;
+
+    pub fn record_contribution(&mut self, currency: PaymentCurrency, amount: u64) -> Result<()> {
+        let contributed = match currency {
+            PaymentCurrency::SOL => &mut self.contributed_lamports,
+            PaymentCurrency::USDT => &mut self.contributed_usdt,
+            PaymentCurrency::USDC => &mut self.contributed_usdc,
+        };
+        *contributed = contributed.checked_add(amount).ok_or(PresaleError::CalculationError)?;
+        Ok(())
+    }
+
+    /// Marks everything vested by `current_time` as claimed and returns the
+    /// amount not claimed before
+    pub fn claim_vested(&mut self, vesting: &VestingTerms, vesting_start: i64, current_time: i64) -> Result<u64> {
+        let vested = vesting.vested_amount(self.total_purchased, current_time.saturating_sub(vesting_start));
+        require!(vested > self.tokens_claimed, PresaleError::NothingToClaim);
+
+        let amount_to_claim = vested - self.tokens_claimed;
+        self.tokens_claimed = vested;
+        self.last_claim_time = current_time;
+        Ok(amount_to_claim)
+    }
+
+    /// Clears the contributions for a refund and returns them as (USDT, USDC, lamports).
+    /// The SOUL entitlement is void, nothing was minted before the presale ended.
+    pub fn take_refund(&mut self) -> Result<(u64, u64, u64)> {
+        let refund = (self.contributed_usdt, self.contributed_usdc, self.contributed_lamports);
+        require!(refund != (0, 0, 0), PresaleError::NothingToRefund);
+
+        self.total_purchased = 0;
+        self.contributed_usdt = 0;
+        self.contributed_usdc = 0;
+        self.contributed_lamports = 0;
+        Ok(refund)
+    }
+
+    /// Adds `tokens` to the wallet's purchases in `stage` and returns the new total
+    pub fn record_stage_purchase(&mut self, stage: u8, tokens: u64) -> Result<u64> {
+        if self.current_stage != stage {
+            self.current_stage = stage;
+            self.current_stage_purchased = 0;
+        }
+        self.current_stage_purchased = self
+            .current_stage_purchased
+            .checked_add(tokens)
+            .ok_or(PresaleError::CalculationError)?;
+        Ok(self.current_stage_purchased)
+    }
+}
+
+#[account]
+pub struct ReferrerInfo {
+    pub referrer: Pubkey,
+    pub referred_usd: u64, // In micro-USD
+    pub bonus_tokens: u64, // Earned, counted against the bonus cap
+    pub bonus_tokens_claimed: u64,
+    pub commission_usd: u64, // Earned, counted against the commission cap
+    pub commission_usdt: u64, // Unclaimed
+    pub commission_usdc: u64,
+}
+
+impl ReferrerInfo {
+    pub const LEN: usize = 32 + 8 + 8 + 8 + 8 + 8 + 8;
 }
 
 #[error_code]
@@ -487,4 +2141,110 @@ pub enum PresaleError {
     CalculationError,
     #[msg("No tokens to burn")]
     NoTokensToBurn,
-} 
\ No newline at end of file
+    #[msg("Invalid SOL/USD price account")]
+    InvalidPriceFeed,
+    #[msg("SOL/USD price is stale")]
+    StalePrice,
+    #[msg("SOL/USD price confidence is too low")]
+    PriceConfidenceTooLow,
+    #[msg("Invalid vesting terms")]
+    InvalidVestingTerms,
+    #[msg("No vested tokens to claim")]
+    NothingToClaim,
+    #[msg("Invalid stage config")]
+    InvalidStageConfig,
+    #[msg("Current stage has ended")]
+    StageEnded,
+    #[msg("Treasury must be a presale vault")]
+    InvalidTreasury,
+    #[msg("Payment token mint does not match the currency")]
+    InvalidPaymentMint,
+    #[msg("Payment token account is not owned by the buyer")]
+    InvalidPaymentAccount,
+    #[msg("Price or token amount is worse than the buyer's limit")]
+    SlippageExceeded,
+    #[msg("Wallet is not on the allowlist")]
+    NotAllowlisted,
+    #[msg("Purchase exceeds the wallet's allowlist allocation")]
+    AllowlistAllocationExceeded,
+    #[msg("Purchase exceeds the maximum ticket size")]
+    TicketTooLarge,
+    #[msg("Purchase exceeds the per-wallet limit")]
+    WalletCapExceeded,
+    #[msg("Purchase exceeds the per-wallet limit of the stage")]
+    WalletStageCapExceeded,
+    #[msg("Soft cap has not been reached")]
+    SoftCapNotReached,
+    #[msg("No contributions to refund")]
+    NothingToRefund,
+    #[msg("Buyers cannot refer themselves")]
+    SelfReferral,
+    #[msg("Invalid referral config")]
+    InvalidReferralConfig,
+    #[msg("Insufficient funds in the vault")]
+    InsufficientFunds,
+    #[msg("No authority transfer is pending")]
+    NoPendingAuthority,
+    #[msg("Signer is not the pending authority")]
+    NotPendingAuthority,
+    #[msg("Signer does not have the required role")]
+    MissingRole,
+    #[msg("Role is already granted")]
+    RoleAlreadyGranted,
+    #[msg("Role is not granted")]
+    RoleNotGranted,
+    #[msg("Roles registry is full")]
+    RoleRegistryFull,
+    #[msg("The last admin cannot be revoked")]
+    LastAdmin,
+    #[msg("Withdrawal exceeds the allowance of the current period")]
+    WithdrawalLimitExceeded,
+    #[msg("Receiver is not an allowlisted treasury destination")]
+    InvalidDestination,
+    #[msg("Invalid treasury policy")]
+    InvalidTreasuryPolicy,
+    #[msg("Treasury split must have unique recipients summing to 10,000 bps")]
+    InvalidTreasurySplit,
+}
+
+impl From<AuthorityError> for PresaleError {
+    fn from(error: AuthorityError) -> Self {
+        match error {
+            AuthorityError::NoPendingAuthority => PresaleError::NoPendingAuthority,
+            AuthorityError::NotPendingAuthority => PresaleError::NotPendingAuthority,
+        }
+    }
+}
+
+impl From<RoleError> for PresaleError {
+    fn from(error: RoleError) -> Self {
+        match error {
+            RoleError::AlreadyGranted => PresaleError::RoleAlreadyGranted,
+            RoleError::NotGranted => PresaleError::RoleNotGranted,
+            RoleError::RegistryFull => PresaleError::RoleRegistryFull,
+            RoleError::LastAdmin => PresaleError::LastAdmin,
+        }
+    }
+}
+
+/// Loads the SOL/USD price, rejecting stale or low-confidence readings.
+pub fn load_sol_usd_price(price_feed: &AccountInfo, now: i64) -> Result<Price> {
+    let feed = load_price_feed_from_account_info(price_feed)
+        .map_err(|_| error!(PresaleError::InvalidPriceFeed))?;
+    let price = feed
+        .get_price_no_older_than(now, MAX_PRICE_AGE_SECONDS)
+        .ok_or(PresaleError::StalePrice)?;
+    require!(price.price > 0, PresaleError::InvalidPriceFeed);
+
+    let max_conf = price.price as u128 * MAX_PRICE_CONFIDENCE_BPS as u128 / 10_000;
+    require!(price.conf as u128 <= max_conf, PresaleError::PriceConfidenceTooLow);
+
+    Ok(price)
+}
+
+/// Converts a USD amount in micro-USD into lamports at the given SOL/USD price.
+/// Rounds up so the treasury never receives less than the quoted amount.
+pub fn usd_to_lamports(amount_usd: u64, price: &Price) -> Result<u64> {
+    math::usd_to_lamports(amount_usd, price.price, price.expo)
+        .ok_or(error!(PresaleError::CalculationError))
+}
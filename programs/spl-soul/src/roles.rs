@@ -0,0 +1,74 @@
+//! Roles registry shared by both programs. Each program keeps its own
+//! `[b"roles"]` account; admins grant and revoke the other roles. The
+//! multisig signer PDA is the first admin, so every grant needs the threshold.
+
+use anchor_lang::prelude::*;
+
+pub const MAX_ROLE_MEMBERS: usize = 16;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Role {
+    Admin,     // Grants and revokes roles
+    Operator,  // Stage changes, burns and vesting unlocks
+    Treasurer, // Withdrawals and treasury mints
+    Pauser,    // Pauses and unpauses the pre-sale
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct RoleMember {
+    pub role: Role,
+    pub member: Pubkey,
+}
+
+impl RoleMember {
+    pub const LEN: usize = 1 + 32;
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RoleError {
+    AlreadyGranted,
+    NotGranted,
+    RegistryFull,
+    LastAdmin,
+}
+
+#[account]
+pub struct RoleRegistry {
+    pub members: Vec<RoleMember>,
+}
+
+impl RoleRegistry {
+    pub const LEN: usize = 4 + RoleMember::LEN * MAX_ROLE_MEMBERS;
+
+    pub fn has_role(&self, role: Role, member: &Pubkey) -> bool {
+        self.members
+            .iter()
+            .any(|entry| entry.role == role && entry.member == *member)
+    }
+
+    pub fn grant(&mut self, role: Role, member: Pubkey) -> std::result::Result<(), RoleError> {
+        if self.has_role(role, &member) {
+            return Err(RoleError::AlreadyGranted);
+        }
+        if self.members.len() >= MAX_ROLE_MEMBERS {
+            return Err(RoleError::RegistryFull);
+        }
+        self.members.push(RoleMember { role, member });
+        Ok(())
+    }
+
+    /// The last admin cannot be revoked, so roles stay manageable
+    pub fn revoke(&mut self, role: Role, member: &Pubkey) -> std::result::Result<(), RoleError> {
+        let position = self
+            .members
+            .iter()
+            .position(|entry| entry.role == role && entry.member == *member)
+            .ok_or(RoleError::NotGranted)?;
+        let admins = self.members.iter().filter(|entry| entry.role == Role::Admin).count();
+        if role == Role::Admin && admins == 1 {
+            return Err(RoleError::LastAdmin);
+        }
+        self.members.remove(position);
+        Ok(())
+    }
+}
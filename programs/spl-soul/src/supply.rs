@@ -0,0 +1,81 @@
+use anchor_lang::prelude::*;
+
+use crate::TokenError;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum SupplyAllocation {
+    Vesting, // Team, development and community schedules
+    DexLiquidity,
+    CexMarketing,
+    Presale,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct AllocationSupply {
+    pub cap: u64,
+    pub minted: u64,
+}
+
+impl AllocationSupply {
+    pub const LEN: usize = 8 + 8;
+}
+
+/// Every SOUL mint, in both programs, is recorded here and checked against the caps
+#[account]
+pub struct SupplyLedger {
+    pub total_cap: u64,
+    pub total_minted: u64,
+    pub vesting: AllocationSupply,
+    pub dex_liquidity: AllocationSupply,
+    pub cex_marketing: AllocationSupply,
+    pub presale: AllocationSupply,
+}
+
+impl SupplyLedger {
+    pub const LEN: usize = 8 + 8 + AllocationSupply::LEN * 4;
+
+    pub fn record_mint(&mut self, allocation: SupplyAllocation, amount: u64) -> Result<()> {
+        let supply = match allocation {
+            SupplyAllocation::Vesting => &mut self.vesting,
+            SupplyAllocation::DexLiquidity => &mut self.dex_liquidity,
+            SupplyAllocation::CexMarketing => &mut self.cex_marketing,
+            SupplyAllocation::Presale => &mut self.presale,
+        };
+
+        let minted = supply.minted.checked_add(amount).ok_or(TokenError::SupplyCapExceeded)?;
+        require!(minted <= supply.cap, TokenError::SupplyCapExceeded);
+        let total_minted = self.total_minted.checked_add(amount).ok_or(TokenError::SupplyCapExceeded)?;
+        require!(total_minted <= self.total_cap, TokenError::SupplyCapExceeded);
+
+        supply.minted = minted;
+        self.total_minted = total_minted;
+        Ok(())
+    }
+
+    /// Counts tokens minted outside the ledger, e.g. before the mint authority moved to the PDA
+    pub fn record_existing_supply(&mut self, mint_supply: u64) -> Result<()> {
+        require!(mint_supply <= self.total_cap, TokenError::SupplyCapExceeded);
+
+        self.total_minted = mint_supply;
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct ScheduleSupply {
+    pub allocation: u8,
+    pub cap: u64,
+    pub minted: u64,
+}
+
+/// Returned by `report_supply`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct SupplyReport {
+    pub total_cap: u64,
+    pub total_minted: u64,
+    pub vesting: AllocationSupply,
+    pub dex_liquidity: AllocationSupply,
+    pub cex_marketing: AllocationSupply,
+    pub presale: AllocationSupply,
+    pub schedules: Vec<ScheduleSupply>,
+}
@@ -0,0 +1,75 @@
+//! Timelocked execution queue. Actions carry an instruction for either program
+//! that can be executed by anyone between its `eta` and the end of the grace
+//! period, signed by the `[b"timelock_signer"]` PDA. Only the multisig signer
+//! can queue and cancel actions. The configuration instructions of both programs
+//! require that PDA as signer, which gives advance notice of every change.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::Instruction;
+
+use crate::multisig::{self, signed_instruction, ProposalAccount, MAX_PROPOSAL_ACCOUNTS, MAX_PROPOSAL_DATA};
+use crate::TokenError;
+
+/// Lowest minimum delay the timelock can be configured with
+pub const MIN_TIMELOCK_DELAY: i64 = 24 * 60 * 60;
+/// How long after its eta a queued action can still be executed
+pub const TIMELOCK_GRACE_PERIOD: i64 = 14 * 24 * 60 * 60;
+
+#[account]
+pub struct Timelock {
+    pub admin: Pubkey, // Queues and cancels actions, always the multisig signer
+    pub min_delay: i64,
+    pub action_count: u64,
+}
+
+impl Timelock {
+    pub const LEN: usize = 32 + 8 + 8;
+
+    /// Sets the admin and minimum delay. The admin has to be the multisig signer
+    /// so no single key can queue configuration changes.
+    pub fn configure(&mut self, admin: Pubkey, min_delay: i64) -> Result<()> {
+        require_keys_eq!(admin, multisig::multisig_signer(), TokenError::InvalidTimelockAdmin);
+        require!(min_delay >= MIN_TIMELOCK_DELAY, TokenError::DelayTooShort);
+
+        self.admin = admin;
+        self.min_delay = min_delay;
+        Ok(())
+    }
+
+    pub fn eta(&self, now: i64, delay: i64) -> Result<i64> {
+        require!(delay >= self.min_delay, TokenError::DelayTooShort);
+        Ok(now.checked_add(delay).ok_or(TokenError::MathOverflow)?)
+    }
+}
+
+#[account]
+pub struct QueuedAction {
+    pub index: u64,
+    pub eta: i64,
+    pub program_id: Pubkey,
+    pub accounts: Vec<ProposalAccount>,
+    pub data: Vec<u8>,
+    pub executed: bool,
+}
+
+impl QueuedAction {
+    pub fn space(accounts: usize, data: usize) -> usize {
+        8 + 8 + 8 + 32 + 4 + ProposalAccount::LEN * accounts + 4 + data + 1
+    }
+
+    pub fn fits(accounts: usize, data: usize) -> bool {
+        accounts <= MAX_PROPOSAL_ACCOUNTS && data <= MAX_PROPOSAL_DATA
+    }
+
+    pub fn check_executable(&self, now: i64) -> Result<()> {
+        require!(!self.executed, TokenError::ActionAlreadyExecuted);
+        require!(now >= self.eta, TokenError::TooEarly);
+        require!(now - self.eta <= TIMELOCK_GRACE_PERIOD, TokenError::ActionExpired);
+        Ok(())
+    }
+
+    /// The queued instruction, with the timelock signer PDA marked as signer
+    pub fn instruction(&self, timelock_signer: &Pubkey) -> Instruction {
+        signed_instruction(self.program_id, &self.accounts, &self.data, timelock_signer)
+    }
+}
@@ -0,0 +1,88 @@
+use anchor_lang::prelude::*;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ReleaseMode {
+    Linear,   // Released continuously from the cliff until the last period ends
+    Periodic, // One tranche at the cliff, then one at the start of every following period
+}
+
+/// Vesting schedule applied to an amount from its start time (TGE).
+/// After the TGE share, nothing is released until `cliff_seconds` have passed,
+/// then the rest is released over `period_count` periods of `period_seconds`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct VestingTerms {
+    pub tge_percentage: u8,
+    pub cliff_seconds: i64,
+    pub period_seconds: i64,
+    pub period_count: u16,
+    pub release: ReleaseMode,
+}
+
+impl VestingTerms {
+    pub const LEN: usize = 1 + 8 + 8 + 2 + 1;
+
+    pub fn is_valid(&self) -> bool {
+        if self.tge_percentage > 100 || self.cliff_seconds < 0 {
+            return false;
+        }
+        if self.period_count == 0 {
+            // Without periods everything has to be released at TGE
+            return self.tge_percentage == 100;
+        }
+        self.period_seconds > 0
+    }
+
+    /// Amount of `total` vested `elapsed` seconds after the start time,
+    /// with the part after TGE split evenly between the periods.
+    pub fn vested_amount(&self, total: u64, elapsed: i64) -> u64 {
+        if elapsed < 0 {
+            return 0;
+        }
+        let (tge_amount, remaining) = self.split_tge(total);
+        if self.period_count == 0 {
+            return total;
+        }
+
+        let (periods, scale) = self.periods_elapsed(elapsed);
+        let released = remaining * periods / (scale * self.period_count as u128);
+        (tge_amount + released) as u64
+    }
+
+    /// Amount of `total` vested `elapsed` seconds after the start time,
+    /// releasing a fixed `amount_per_period` after TGE until `total` is reached.
+    pub fn vested_amount_in_tranches(&self, total: u64, elapsed: i64, amount_per_period: u64) -> u64 {
+        if elapsed < 0 {
+            return 0;
+        }
+        let (tge_amount, remaining) = self.split_tge(total);
+
+        let (periods, scale) = self.periods_elapsed(elapsed);
+        let released = (amount_per_period as u128 * periods / scale).min(remaining);
+        (tge_amount + released) as u64
+    }
+
+    fn split_tge(&self, total: u64) -> (u128, u128) {
+        let tge_amount = total as u128 * self.tge_percentage as u128 / 100;
+        (tge_amount, total as u128 - tge_amount)
+    }
+
+    /// Periods released after the cliff, as the fraction `periods / scale`
+    fn periods_elapsed(&self, elapsed: i64) -> (u128, u128) {
+        let since_cliff = elapsed - self.cliff_seconds;
+        if since_cliff < 0 || self.period_count == 0 {
+            return (0, 1);
+        }
+
+        let period_count = self.period_count as u128;
+        match self.release {
+            ReleaseMode::Linear => {
+                let vesting_duration = self.period_seconds as u128 * period_count;
+                ((since_cliff as u128).min(vesting_duration), self.period_seconds as u128)
+            }
+            ReleaseMode::Periodic => {
+                let periods = since_cliff as u128 / self.period_seconds as u128 + 1;
+                (periods.min(period_count), 1)
+            }
+        }
+    }
+}
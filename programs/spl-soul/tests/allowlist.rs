@@ -0,0 +1,57 @@
+use anchor_lang::prelude::*;
+use spl_soul::allowlist::*;
+
+fn wallets(count: usize) -> Vec<Pubkey> {
+    (0..count).map(|_| Pubkey::new_unique()).collect()
+}
+
+fn csv(wallets: &[Pubkey]) -> String {
+    let mut csv = String::from("wallet,allocation\n");
+    for (index, wallet) in wallets.iter().enumerate() {
+        csv.push_str(&format!("{},{}\n", wallet, (index as u64 + 1) * 1_000_000));
+    }
+    csv
+}
+
+#[test]
+fn proves_every_wallet() {
+    // Odd sizes carry a node up unchanged
+    for count in [1, 2, 5, 8] {
+        let wallets = wallets(count);
+        let tree = AllowlistTree::from_csv(&csv(&wallets)).unwrap();
+        for (index, wallet) in wallets.iter().enumerate() {
+            let (allocation, proof) = tree.proof(wallet).unwrap();
+            assert_eq!(allocation, (index as u64 + 1) * 1_000_000);
+            assert!(verify(&tree.root(), leaf(wallet, allocation), &proof));
+        }
+    }
+}
+
+#[test]
+fn rejects_wrong_allocation_or_wallet() {
+    let wallets = wallets(5);
+    let tree = AllowlistTree::from_csv(&csv(&wallets)).unwrap();
+    let (allocation, proof) = tree.proof(&wallets[2]).unwrap();
+
+    assert!(!verify(&tree.root(), leaf(&wallets[2], allocation + 1), &proof));
+    assert!(!verify(&tree.root(), leaf(&wallets[3], allocation), &proof));
+    assert!(tree.proof(&Pubkey::new_unique()).is_none());
+}
+
+#[test]
+fn rejects_invalid_csv() {
+    let wallet = Pubkey::new_unique();
+    assert_eq!(
+        AllowlistTree::from_csv(&format!("{wallet},100\n{wallet},200")).err(),
+        Some(AllowlistCsvError::DuplicateWallet(wallet))
+    );
+    assert_eq!(
+        AllowlistTree::from_csv(&format!("{wallet},100\nnot-a-wallet,5")).err(),
+        Some(AllowlistCsvError::InvalidLine(2))
+    );
+    assert_eq!(
+        AllowlistTree::from_csv(&format!("{wallet},-5")).err(),
+        Some(AllowlistCsvError::InvalidLine(1))
+    );
+    assert_eq!(AllowlistTree::from_csv("wallet,allocation\n").err(), Some(AllowlistCsvError::Empty));
+}
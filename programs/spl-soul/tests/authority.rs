@@ -0,0 +1,112 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use spl_soul::authority::{self, AuthorityError};
+use spl_soul::config::AllocationConfig;
+use spl_soul::presale::PresaleError;
+use spl_soul::{TokenError, TokenState};
+
+fn token_state() -> TokenState {
+    TokenState {
+        authority: Pubkey::new_unique(),
+        pending_authority: Pubkey::default(),
+        mint: Pubkey::new_unique(),
+        total_supply: 0,
+        dex_liquidity_supply: 0,
+        cex_marketing_supply: 0,
+        cex_marketing_unlocked: 0,
+        cex_marketing_drawn: 0,
+        vesting_supply: 0,
+        tge_time: 0,
+        config: AllocationConfig::soul(),
+    }
+}
+
+#[test]
+fn only_the_proposed_key_can_accept() {
+    let mut state = token_state();
+    let authority = state.authority;
+    let new_authority = Pubkey::new_unique();
+    state.propose_authority(new_authority);
+
+    assert_eq!(
+        state.accept_authority(&Pubkey::new_unique()),
+        Err(TokenError::NotPendingAuthority.into())
+    );
+    assert_eq!(state.accept_authority(&authority), Err(TokenError::NotPendingAuthority.into()));
+    assert_eq!(state.authority, authority);
+
+    assert_eq!(state.accept_authority(&new_authority), Ok(authority));
+    assert_eq!(state.authority, new_authority);
+    assert_eq!(state.pending_authority, Pubkey::default());
+}
+
+#[test]
+fn cannot_accept_after_cancel() {
+    let mut state = token_state();
+    let authority = state.authority;
+    let new_authority = Pubkey::new_unique();
+    state.propose_authority(new_authority);
+
+    assert_eq!(state.cancel_authority_transfer(), Ok(new_authority));
+    assert_eq!(state.accept_authority(&new_authority), Err(TokenError::NoPendingAuthority.into()));
+    assert_eq!(state.authority, authority);
+    assert_eq!(state.cancel_authority_transfer(), Err(TokenError::NoPendingAuthority.into()));
+}
+
+#[test]
+fn second_proposal_replaces_the_first() {
+    let mut state = token_state();
+    let first = Pubkey::new_unique();
+    let second = Pubkey::new_unique();
+    state.propose_authority(first);
+    state.propose_authority(second);
+
+    assert_eq!(state.accept_authority(&first), Err(TokenError::NotPendingAuthority.into()));
+    assert!(state.accept_authority(&second).is_ok());
+    assert_eq!(state.authority, second);
+}
+
+#[test]
+fn presale_state_uses_the_same_handover() {
+    let mut presale = common::presale_state(Vec::new());
+    let authority = presale.authority;
+    let new_authority = Pubkey::new_unique();
+    presale.propose_authority(new_authority);
+
+    assert_eq!(
+        presale.accept_authority(&authority),
+        Err(PresaleError::NotPendingAuthority.into())
+    );
+    assert_eq!(presale.cancel_authority_transfer(), Ok(new_authority));
+    assert_eq!(
+        presale.accept_authority(&new_authority),
+        Err(PresaleError::NoPendingAuthority.into())
+    );
+
+    presale.propose_authority(new_authority);
+    assert_eq!(presale.accept_authority(&new_authority), Ok(authority));
+    assert_eq!(presale.authority, new_authority);
+    assert_eq!(presale.pending_authority, Pubkey::default());
+}
+
+#[test]
+fn helper_leaves_the_authority_alone_on_error() {
+    let mut current = Pubkey::new_unique();
+    let mut pending = Pubkey::default();
+    let signer = Pubkey::new_unique();
+
+    assert_eq!(authority::cancel(&mut pending), Err(AuthorityError::NoPendingAuthority));
+    assert_eq!(
+        authority::accept(&mut current, &mut pending, &signer),
+        Err(AuthorityError::NoPendingAuthority)
+    );
+
+    authority::propose(&mut pending, signer);
+    let before = current;
+    assert_eq!(
+        authority::accept(&mut current, &mut pending, &Pubkey::new_unique()),
+        Err(AuthorityError::NotPendingAuthority)
+    );
+    assert_eq!((current, pending), (before, signer));
+}
@@ -0,0 +1,70 @@
+//! Fixtures shared by the integration tests. Each test binary only uses some
+//! of them.
+#![allow(dead_code)]
+
+use anchor_lang::prelude::Pubkey;
+use spl_soul::presale::*;
+use spl_soul::vesting::{ReleaseMode, VestingTerms};
+
+/// An active pre-sale at the first of `stages`, with every other setting at
+/// its initial value. Tests override the fields they exercise.
+pub fn presale_state(stages: Vec<StageConfig>) -> PresaleState {
+    PresaleState {
+        authority: Pubkey::new_unique(),
+        pending_authority: Pubkey::default(),
+        soul_mint: Pubkey::new_unique(),
+        usdt_mint: Pubkey::new_unique(),
+        usdc_mint: Pubkey::new_unique(),
+        treasury_wallet: Pubkey::new_unique(),
+        sol_price_feed: Pubkey::new_unique(),
+        usdt_vault: Pubkey::new_unique(),
+        usdc_vault: Pubkey::new_unique(),
+        soul_decimals: 6,
+        usdt_decimals: 6,
+        usdc_decimals: 6,
+        stage: PresaleStage::Active,
+        current_stage: 0,
+        stage_tokens_remaining: stages.first().map_or(0, |stage| stage.allocation),
+        tokens_sold_current_stage: 0,
+        total_tokens_sold: 0,
+        bonus_tokens_current_stage: 0,
+        total_bonus_tokens: 0,
+        unsold_tokens: 0,
+        allowlist_root: [0; 32],
+        allowlist_end_time: 0,
+        limits: PurchaseLimits::default(),
+        soft_cap_usd: 0,
+        total_raised_usd: 0,
+        referral: ReferralConfig::default(),
+        referral_owed_usdt: 0,
+        referral_owed_usdc: 0,
+        treasury_split: TreasurySplit::default(),
+        is_paused: false,
+        min_purchase_amount: 50_000_000,
+        stages,
+        vesting: VestingTerms {
+            tge_percentage: 100,
+            cliff_seconds: 0,
+            period_seconds: 1,
+            period_count: 1,
+            release: ReleaseMode::Linear,
+        },
+        vesting_start: 0,
+    }
+}
+
+/// A buyer that has not purchased anything yet
+pub fn user_info() -> UserPresaleInfo {
+    UserPresaleInfo {
+        wallet: Pubkey::new_unique(),
+        total_purchased: 0,
+        tokens_claimed: 0,
+        last_claim_time: 0,
+        allowlist_purchased: 0,
+        current_stage: 0,
+        current_stage_purchased: 0,
+        contributed_usdt: 0,
+        contributed_usdc: 0,
+        contributed_lamports: 0,
+    }
+}
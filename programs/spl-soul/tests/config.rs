@@ -0,0 +1,99 @@
+use spl_soul::config::*;
+use spl_soul::math::{bps_of, to_base_units};
+use spl_soul::vesting::ReleaseMode;
+
+const DAY: i64 = 24 * 60 * 60;
+
+#[test]
+fn soul_preset_is_valid() {
+    let config = AllocationConfig::soul();
+    assert!(config.is_valid());
+    assert_eq!(config.decimals, 6);
+    assert_eq!(config.vesting.len(), 3);
+}
+
+#[test]
+fn rejects_invalid_configs() {
+    let mut config = AllocationConfig::soul();
+    config.presale_bps += 1;
+    assert!(!config.is_valid());
+
+    let mut config = AllocationConfig::soul();
+    config.vesting[1].allocation = TEAM_ALLOCATION;
+    assert!(!config.is_valid());
+
+    let mut config = AllocationConfig::soul();
+    config.vesting[0].terms.period_count = 0;
+    assert!(!config.is_valid());
+
+    let mut config = AllocationConfig::soul();
+    config.vesting[2].period_bps = config.vesting[2].supply_bps + 1;
+    assert!(!config.is_valid());
+
+    let mut config = AllocationConfig::soul();
+    config.decimals = MAX_DECIMALS + 1;
+    assert!(!config.is_valid());
+}
+
+#[test]
+fn accepts_other_splits() {
+    // 20% presale, a fourth vesting bucket and 9 decimals
+    let mut config = AllocationConfig::soul();
+    config.decimals = 9;
+    config.presale_bps = 2_000;
+    let mut advisors = config.vesting[0];
+    advisors.allocation = 3;
+    advisors.supply_bps = 1_000;
+    config.vesting.push(advisors);
+    assert!(config.is_valid());
+}
+
+#[test]
+fn soul_preset_matches_original_schedules() {
+    let config = AllocationConfig::soul();
+    let total_supply = to_base_units(10_000_000_000, config.decimals as u32).unwrap();
+
+    // Team: 25% every 6 months after a 6 month cliff
+    let team = config.vesting_bucket(TEAM_ALLOCATION).unwrap();
+    let team_supply = bps_of(total_supply, team.supply_bps as u64).unwrap();
+    assert!(team.terms.release == ReleaseMode::Periodic);
+    assert_eq!(team.terms.vested_amount(team_supply, 180 * DAY - 1), 0);
+    assert_eq!(team.terms.vested_amount(team_supply, 180 * DAY), team_supply / 4);
+
+    // Development: one sixth at TGE, then one more every 180 days
+    let development = config.vesting_bucket(DEVELOPMENT_ALLOCATION).unwrap();
+    let development_supply = bps_of(total_supply, development.supply_bps as u64).unwrap();
+    assert!(development.terms.release == ReleaseMode::Periodic);
+    assert_eq!(development.terms.vested_amount(development_supply, 0), development_supply / 6);
+    assert_eq!(development.terms.vested_amount(development_supply, 180 * DAY - 1), development_supply / 6);
+    assert_eq!(development.terms.vested_amount(development_supply, 180 * DAY), development_supply / 3);
+    assert_eq!(development.terms.vested_amount(development_supply, 900 * DAY), development_supply);
+
+    // Community: 30% at TGE, then 200M SOUL every 60 days
+    let community = config.vesting_bucket(COMMUNITY_ALLOCATION).unwrap();
+    let community_supply = bps_of(total_supply, community.supply_bps as u64).unwrap();
+    let amount_per_period = bps_of(total_supply, community.period_bps as u64).unwrap();
+    let at_tge = community_supply * 30 / 100;
+    let vested = |elapsed| {
+        community
+            .terms
+            .vested_amount_in_tranches(community_supply, elapsed, amount_per_period)
+    };
+    assert_eq!(vested(0), at_tge);
+    assert_eq!(vested(60 * DAY), at_tge + 200_000_000_000_000);
+
+    assert!(config.vesting_bucket(3).is_none());
+}
+
+#[test]
+fn community_tranche_follows_the_decimals() {
+    // 200M SOUL per period whatever the mint's decimals
+    let mut config = AllocationConfig::soul();
+    config.decimals = 9;
+    let total_supply = to_base_units(10_000_000_000, config.decimals as u32).unwrap();
+    let community = config.vesting_bucket(COMMUNITY_ALLOCATION).unwrap();
+    assert_eq!(
+        bps_of(total_supply, community.period_bps as u64),
+        to_base_units(200_000_000, 9)
+    );
+}
@@ -0,0 +1,42 @@
+use spl_soul::math::*;
+
+#[test]
+fn splits_total_supply() {
+    let total_supply = to_base_units(10_000_000_000, SOUL_DECIMALS).unwrap();
+    assert_eq!(total_supply, 10_000_000_000_000_000);
+    assert_eq!(bps_of(total_supply, 1_500), Some(1_500_000_000_000_000));
+    assert_eq!(checked_sum(&[u64::MAX, 1]), None);
+}
+
+#[test]
+fn rejects_overflowing_supply() {
+    assert_eq!(to_base_units(u64::MAX / 10, SOUL_DECIMALS), None);
+    assert_eq!(bps_of(u64::MAX, 10_001), None);
+}
+
+#[test]
+fn prices_stage_purchases() {
+    // $50 at $0.005 per token buys 10,000 SOUL
+    assert_eq!(tokens_for_usd(50_000_000, 5_000, SOUL_DECIMALS), Some(10_000_000_000));
+    assert_eq!(tokens_for_usd(50_000_000, 9_800, SOUL_DECIMALS), Some(5_102_040_816));
+    assert_eq!(tokens_for_usd(50_000_000, 0, SOUL_DECIMALS), None);
+    assert_eq!(tokens_for_usd(u64::MAX, 1, SOUL_DECIMALS), None);
+}
+
+#[test]
+fn converts_usd_to_stablecoins() {
+    // $50 in 6 and 12 decimal stablecoins
+    assert_eq!(usd_to_stablecoin(50_000_000, 6), Some(50_000_000));
+    assert_eq!(usd_to_stablecoin(50_000_000, 12), Some(50 * 10u64.pow(12)));
+    // A 2 decimal stablecoin rounds one micro-USD up to a full cent
+    assert_eq!(usd_to_stablecoin(1, 2), Some(1));
+    assert_eq!(usd_to_stablecoin(u64::MAX, 18), None);
+}
+
+#[test]
+fn converts_usd_to_lamports() {
+    // $100 per SOL with a positive and a negative exponent
+    assert_eq!(usd_to_lamports(50_000_000, 1, 2), Some(500_000_000));
+    assert_eq!(usd_to_lamports(50_000_000, 10_000_000_000, -8), Some(500_000_000));
+    assert_eq!(usd_to_lamports(50_000_000, 0, -8), None);
+}
@@ -0,0 +1,65 @@
+use anchor_lang::prelude::Pubkey;
+use spl_soul::multisig::*;
+
+fn multisig(threshold: u8) -> Multisig {
+    Multisig {
+        owners: (0..3).map(|_| Pubkey::new_unique()).collect(),
+        threshold,
+        owner_set_seqno: 0,
+        proposal_count: 0,
+    }
+}
+
+fn proposal(approvals: Vec<bool>) -> MultisigProposal {
+    MultisigProposal {
+        index: 0,
+        proposer: Pubkey::new_unique(),
+        program_id: Pubkey::new_unique(),
+        accounts: vec![],
+        data: vec![],
+        approvals,
+        owner_set_seqno: 0,
+        executed: false,
+    }
+}
+
+#[test]
+fn validates_owner_sets() {
+    let owners: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+    assert!(Multisig::is_valid_owner_set(&owners, 2));
+    assert!(!Multisig::is_valid_owner_set(&owners, 0));
+    assert!(!Multisig::is_valid_owner_set(&owners, 4));
+    assert!(!Multisig::is_valid_owner_set(&[owners[0], owners[0]], 1));
+
+    let too_many: Vec<Pubkey> = (0..=MAX_MULTISIG_OWNERS).map(|_| Pubkey::new_unique()).collect();
+    assert!(!Multisig::is_valid_owner_set(&too_many, 2));
+}
+
+#[test]
+fn requires_threshold_approvals_from_current_owners() {
+    let mut multisig = multisig(2);
+    assert!(!proposal(vec![true, false, false]).is_approved(&multisig));
+    assert!(proposal(vec![true, false, true]).is_approved(&multisig));
+
+    // Changing the owners voids open proposals
+    multisig.owner_set_seqno = 1;
+    assert!(!proposal(vec![true, true, true]).is_approved(&multisig));
+}
+
+#[test]
+fn signs_proposed_instruction_with_multisig_signer() {
+    let signer = Pubkey::new_unique();
+    let state = Pubkey::new_unique();
+    let mut proposal = proposal(vec![true]);
+    proposal.accounts = vec![
+        ProposalAccount { pubkey: signer, is_signer: false, is_writable: false },
+        ProposalAccount { pubkey: state, is_signer: false, is_writable: true },
+    ];
+    proposal.data = vec![1, 2, 3];
+
+    let instruction = proposal.instruction(&signer);
+    assert_eq!(instruction.program_id, proposal.program_id);
+    assert!(instruction.accounts[0].is_signer);
+    assert!(!instruction.accounts[1].is_signer && instruction.accounts[1].is_writable);
+    assert_eq!(instruction.data, vec![1, 2, 3]);
+}
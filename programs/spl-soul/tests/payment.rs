@@ -0,0 +1,44 @@
+mod common;
+
+use anchor_lang::prelude::*;
+use spl_soul::presale::*;
+
+fn presale_state() -> PresaleState {
+    common::presale_state(soul_stages(0))
+}
+
+#[test]
+fn accepts_matching_mint_and_vault() {
+    let presale = presale_state();
+    assert!(presale
+        .validate_payment(PaymentCurrency::USDT, &presale.usdt_mint, &presale.usdt_vault)
+        .is_ok());
+    assert!(presale
+        .validate_payment(PaymentCurrency::USDC, &presale.usdc_mint, &presale.usdc_vault)
+        .is_ok());
+}
+
+#[test]
+fn rejects_fake_mint() {
+    let presale = presale_state();
+    let fake_mint = Pubkey::new_unique();
+    assert_eq!(
+        presale.validate_payment(PaymentCurrency::USDT, &fake_mint, &presale.usdt_vault),
+        Err(PresaleError::InvalidPaymentMint.into())
+    );
+
+    // USDC paid as USDT is rejected too
+    assert_eq!(
+        presale.validate_payment(PaymentCurrency::USDT, &presale.usdc_mint, &presale.usdt_vault),
+        Err(PresaleError::InvalidPaymentMint.into())
+    );
+}
+
+#[test]
+fn rejects_other_vault() {
+    let presale = presale_state();
+    assert_eq!(
+        presale.validate_payment(PaymentCurrency::USDC, &presale.usdc_mint, &presale.usdt_vault),
+        Err(PresaleError::InvalidTreasury.into())
+    );
+}
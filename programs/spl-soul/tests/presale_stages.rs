@@ -0,0 +1,55 @@
+use spl_soul::presale::*;
+
+#[test]
+fn soul_stages_are_valid() {
+    let stages = soul_stages(1_700_000_000);
+    assert_eq!(stages.len(), 3);
+    assert_eq!(stages[2].price, 9_800);
+    assert_eq!(stages[1].start_time, stages[0].end_time);
+    assert!(stages_are_valid(&stages));
+}
+
+#[test]
+fn accepts_five_stages_of_different_lengths() {
+    let mut stages = Vec::new();
+    let mut start_time = 1_700_000_000;
+    for (index, days) in [7, 14, 21, 30, 45].iter().enumerate() {
+        let end_time = start_time + days * 24 * 60 * 60;
+        stages.push(StageConfig {
+            price: 5_000 + index as u64 * 1_000,
+            allocation: 500_000_000_000_000,
+            start_time,
+            end_time,
+            carry_over: if index == 2 { CarryOver::Burn } else { CarryOver::NextStage },
+        });
+        start_time = end_time;
+    }
+    assert!(stages_are_valid(&stages));
+}
+
+#[test]
+fn rejects_invalid_stages() {
+    let mut stages = soul_stages(1_700_000_000);
+    stages[1].start_time = stages[0].end_time - 1;
+    assert!(!stages_are_valid(&stages));
+
+    let mut stages = soul_stages(1_700_000_000);
+    stages[0].price = 0;
+    assert!(!stages_are_valid(&stages));
+
+    let mut stages = soul_stages(1_700_000_000);
+    stages[2].end_time = stages[2].start_time;
+    assert!(!stages_are_valid(&stages));
+
+    let stages: Vec<StageConfig> = (0..MAX_STAGES as i64 + 1)
+        .map(|index| StageConfig {
+            price: 50,
+            allocation: 1,
+            start_time: index * 10,
+            end_time: index * 10 + 10,
+            carry_over: CarryOver::NextStage,
+        })
+        .collect();
+    assert!(stages_are_valid(&stages[..MAX_STAGES]));
+    assert!(!stages_are_valid(&stages));
+}
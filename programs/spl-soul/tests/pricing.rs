@@ -0,0 +1,71 @@
+use proptest::prelude::*;
+use spl_soul::math::*;
+
+proptest! {
+    // Tokens received times the price never exceed the amount paid, and fall
+    // short of it by less than one SOUL base unit
+    #[test]
+    fn tokens_match_amount_paid(
+        amount_usd in 1u64..=1_000_000_000_000,
+        price in 1u64..=100_000_000,
+        decimals in 0u32..=9,
+    ) {
+        let paid = amount_usd as u128 * 10u128.pow(decimals);
+        // Large orders at tiny prices buy more base units than fit in a u64
+        let Some(tokens) = tokens_for_usd(amount_usd, price, decimals) else {
+            prop_assert!(paid / price as u128 > u64::MAX as u128);
+            return Ok(());
+        };
+        let value = tokens as u128 * price as u128;
+        prop_assert!(value <= paid);
+        prop_assert!(paid - value < price as u128);
+    }
+
+    // Filling a stage remainder never costs more than the order that asked for it
+    #[test]
+    fn stage_remainder_cost_fits_order(
+        amount_usd in 1u64..=1_000_000_000_000,
+        price in 1u64..=100_000_000,
+        decimals in 0u32..=9,
+    ) {
+        // Orders too large to quote are covered by tokens_match_amount_paid
+        let Some(tokens) = tokens_for_usd(amount_usd, price, decimals) else {
+            return Ok(());
+        };
+        let cost = usd_for_tokens(tokens, price, decimals).unwrap();
+        prop_assert!(cost <= amount_usd);
+        prop_assert!(cost as u128 * 10u128.pow(decimals) >= tokens as u128 * price as u128);
+    }
+
+    // Stablecoin payments cover the quoted amount, overpaying by less than one base unit
+    #[test]
+    fn stablecoin_payment_covers_amount(
+        amount_usd in 0u64..=1_000_000_000_000,
+        decimals in 0u32..=12,
+    ) {
+        let paid = usd_to_stablecoin(amount_usd, decimals).unwrap() as u128;
+        let quoted = amount_usd as u128 * 10u128.pow(decimals);
+        prop_assert!(paid * 1_000_000 >= quoted);
+        prop_assert!(paid * 1_000_000 - quoted < 1_000_000);
+    }
+
+    // SOL payments cover the quoted amount, overpaying by less than one lamport
+    #[test]
+    fn lamport_payment_covers_amount(
+        amount_usd in 0u64..=1_000_000_000_000,
+        price in 1i64..=1_000_000_000_000,
+        expo in -10i32..=-2,
+    ) {
+        let quoted = amount_usd as u128 * 1_000_000_000 * 10u128.pow(expo.unsigned_abs());
+        // Tiny prices with a small exponent quote more lamports than fit in a u64
+        let Some(lamports) = usd_to_lamports(amount_usd, price, expo) else {
+            prop_assert!(quoted / (price as u128 * 1_000_000) > u64::MAX as u128);
+            return Ok(());
+        };
+        let lamports = lamports as u128;
+        // lamports * price * 10^expo / 10^9 >= micro_usd / 10^6
+        let value = lamports * price as u128 * 1_000_000;
+        prop_assert!(value >= quoted);
+        prop_assert!(value - quoted < price as u128 * 1_000_000);
+    }
+}
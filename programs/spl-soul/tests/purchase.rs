@@ -0,0 +1,249 @@
+mod common;
+
+use anchor_lang::prelude::*;
+use common::user_info;
+use spl_soul::presale::*;
+
+const TOKEN: u64 = 1_000_000; // One SOUL in base units
+const USD: u64 = 1_000_000; // $1 in micro-USD
+const NOW: i64 = 1_700_000_000;
+
+/// Two back-to-back stages of 10,000 SOUL at $0.005 and $0.01, the first one open
+fn presale_state() -> PresaleState {
+    let stages = vec![
+        StageConfig {
+            price: 5_000,
+            allocation: 10_000 * TOKEN,
+            start_time: NOW - 100,
+            end_time: NOW + 100,
+            carry_over: CarryOver::NextStage,
+        },
+        StageConfig {
+            price: 10_000,
+            allocation: 10_000 * TOKEN,
+            start_time: NOW + 100,
+            end_time: NOW + 200,
+            carry_over: CarryOver::NextStage,
+        },
+    ];
+    let mut presale = common::presale_state(stages);
+    presale.min_purchase_amount = 20 * USD;
+    presale
+}
+
+#[test]
+fn order_spans_two_stages() {
+    let mut presale = presale_state();
+    let mut user = user_info();
+
+    // $50 buys out the first stage, the other $50 fills at the second stage price
+    let filled = presale.fill_order(&mut user, 100 * USD, 0, 10_000, NOW);
+    assert_eq!(filled, Ok((15_000 * TOKEN, 100 * USD)));
+
+    assert_eq!(presale.current_stage, 1);
+    assert_eq!(presale.stages[1].start_time, NOW);
+    assert_eq!(presale.stage_tokens_remaining, 5_000 * TOKEN);
+    assert_eq!(presale.tokens_sold_current_stage, 5_000 * TOKEN);
+    assert_eq!(presale.total_tokens_sold, 15_000 * TOKEN);
+    assert_eq!(user.total_purchased, 15_000 * TOKEN);
+    assert_eq!((user.current_stage, user.current_stage_purchased), (1, 5_000 * TOKEN));
+}
+
+#[test]
+fn stops_at_max_price_without_charging_the_rest() {
+    let mut presale = presale_state();
+    let mut user = user_info();
+
+    // Only the $50 filled in the first stage is charged
+    let filled = presale.fill_order(&mut user, 100 * USD, 0, 5_000, NOW);
+    assert_eq!(filled, Ok((10_000 * TOKEN, 50 * USD)));
+    assert_eq!(presale.current_stage, 1);
+    assert_eq!(presale.stage_tokens_remaining, 10_000 * TOKEN);
+    assert_eq!(presale.total_tokens_sold, 10_000 * TOKEN);
+
+    // Nothing left to fill at that price
+    assert_eq!(
+        presale.fill_order(&mut user, 100 * USD, 0, 5_000, NOW),
+        Err(PresaleError::SlippageExceeded.into())
+    );
+}
+
+#[test]
+fn sell_out_of_the_last_stage_is_not_overcharged() {
+    let mut presale = presale_state();
+    let mut user = user_info();
+
+    // $150 buys everything, the remaining $50 is not charged
+    let filled = presale.fill_order(&mut user, 200 * USD, 0, 10_000, NOW);
+    assert_eq!(filled, Ok((20_000 * TOKEN, 150 * USD)));
+    assert!(presale.stage == PresaleStage::Ended);
+    assert_eq!(presale.unsold_tokens, 0);
+}
+
+#[test]
+fn partial_fill_must_meet_the_minimum() {
+    let mut presale = presale_state();
+    let mut user = user_info();
+    presale.stage_tokens_remaining = 2_000 * TOKEN;
+
+    // $10 of the first stage is left, which is below the $20 minimum
+    assert_eq!(
+        presale.fill_order(&mut user, 100 * USD, 0, 5_000, NOW),
+        Err(PresaleError::BelowMinimum.into())
+    );
+    assert_eq!(
+        presale.fill_order(&mut user, 10 * USD, 0, 5_000, NOW),
+        Err(PresaleError::BelowMinimum.into())
+    );
+}
+
+#[test]
+fn sell_out_purchase_counts_toward_the_soft_cap() {
+    // The last purchase is what reaches the soft cap
+    let mut presale = presale_state();
+    presale.soft_cap_usd = 150 * USD;
+    let mut user = user_info();
+    assert!(presale.fill_order(&mut user, 200 * USD, 0, 10_000, NOW).is_ok());
+    assert_eq!(presale.total_raised_usd, 150 * USD);
+    assert!(presale.stage == PresaleStage::Ended);
+    assert_eq!(presale.vesting_start, NOW);
+
+    // One micro-USD short of it
+    let mut presale = presale_state();
+    presale.soft_cap_usd = 150 * USD + 1;
+    let mut user = user_info();
+    assert!(presale.fill_order(&mut user, 200 * USD, 0, 10_000, NOW).is_ok());
+    assert!(presale.stage == PresaleStage::Refunding);
+}
+
+#[test]
+fn refund_returns_every_currency_once() {
+    let mut user = user_info();
+    user.total_purchased = 15_000 * TOKEN;
+    user.record_contribution(PaymentCurrency::USDT, 40_000_000).unwrap();
+    user.record_contribution(PaymentCurrency::USDC, 10_000_000).unwrap();
+    user.record_contribution(PaymentCurrency::SOL, 250_000_000).unwrap();
+    user.record_contribution(PaymentCurrency::USDT, 5_000_000).unwrap();
+
+    assert_eq!(user.take_refund(), Ok((45_000_000, 10_000_000, 250_000_000)));
+    assert_eq!(user.total_purchased, 0);
+    assert_eq!(user.take_refund(), Err(PresaleError::NothingToRefund.into()));
+}
+
+#[test]
+fn enforces_purchase_limits() {
+    let mut presale = presale_state();
+    presale.limits = PurchaseLimits {
+        max_tokens_per_wallet: 14_000 * TOKEN,
+        max_tokens_per_wallet_per_stage: 8_000 * TOKEN,
+        max_ticket_usd: 60 * USD,
+    };
+
+    let mut user = user_info();
+    assert_eq!(
+        presale.fill_order(&mut user, 60 * USD + 1, 0, 10_000, NOW),
+        Err(PresaleError::TicketTooLarge.into())
+    );
+
+    // $50 is 10,000 SOUL of the first stage, over the per-stage cap
+    let mut user = user_info();
+    assert_eq!(
+        presale.clone().fill_order(&mut user, 50 * USD, 0, 10_000, NOW),
+        Err(PresaleError::WalletStageCapExceeded.into())
+    );
+
+    // 8,000 SOUL in the first stage, then 6,000 in the second one after another buyer sold it out
+    let mut user = user_info();
+    assert_eq!(presale.fill_order(&mut user, 40 * USD, 0, 10_000, NOW), Ok((8_000 * TOKEN, 40 * USD)));
+    assert!(presale.fill_order(&mut user_info(), 20 * USD, 0, 10_000, NOW).is_ok());
+    assert_eq!(presale.current_stage, 1);
+    assert_eq!(presale.fill_order(&mut user, 60 * USD, 0, 10_000, NOW), Ok((6_000 * TOKEN, 60 * USD)));
+    assert_eq!(
+        presale.fill_order(&mut user, 20 * USD, 0, 10_000, NOW),
+        Err(PresaleError::WalletCapExceeded.into())
+    );
+}
+
+#[test]
+fn rejects_stage_above_max_price() {
+    let mut presale = presale_state();
+    let mut user = user_info();
+    assert_eq!(
+        presale.fill_order(&mut user, 50 * USD, 0, 4_999, NOW),
+        Err(PresaleError::SlippageExceeded.into())
+    );
+    assert_eq!(presale.fill_order(&mut user, 50 * USD, 0, 5_000, NOW), Ok((10_000 * TOKEN, 50 * USD)));
+}
+
+#[test]
+fn rejects_fill_below_min_tokens_out() {
+    // $100 only fills 10,000 SOUL when the second stage is out of range
+    let mut user = user_info();
+    assert_eq!(
+        presale_state().fill_order(&mut user, 100 * USD, 10_000 * TOKEN + 1, 5_000, NOW),
+        Err(PresaleError::SlippageExceeded.into())
+    );
+
+    // 15,000 SOUL when it rolls over into the second stage
+    assert_eq!(
+        presale_state().fill_order(&mut user, 100 * USD, 15_000 * TOKEN + 1, 10_000, NOW),
+        Err(PresaleError::SlippageExceeded.into())
+    );
+    assert!(presale_state()
+        .fill_order(&mut user_info(), 100 * USD, 15_000 * TOKEN, 10_000, NOW)
+        .is_ok());
+}
+
+#[test]
+fn referral_bonuses_stay_within_the_stage_allocations() {
+    let mut presale = presale_state();
+    presale.referral.bonus_bps = 1_000;
+    presale.min_purchase_amount = USD;
+    let mut referrer = ReferrerInfo {
+        referrer: Pubkey::new_unique(),
+        referred_usd: 0,
+        bonus_tokens: 0,
+        bonus_tokens_claimed: 0,
+        commission_usd: 0,
+        commission_usdt: 0,
+        commission_usdc: 0,
+    };
+
+    let mut sold = 0;
+    let mut bonuses = 0;
+    while let Ok((tokens, amount_usd)) = presale.fill_order(&mut user_info(), 30 * USD, 0, 10_000, NOW) {
+        let (bonus_tokens, _) = presale.referral.rewards(&referrer, tokens, amount_usd).unwrap();
+        let bonus_tokens = presale.reserve_bonus(bonus_tokens, NOW).unwrap();
+        referrer.bonus_tokens += bonus_tokens;
+        sold += tokens;
+        bonuses += bonus_tokens;
+    }
+
+    assert!(presale.stage == PresaleStage::Ended);
+    assert!(bonuses > 0);
+    assert_eq!(sold + bonuses + presale.unsold_tokens, 20_000 * TOKEN);
+    assert_eq!(presale.total_tokens_sold, sold);
+    assert_eq!(presale.total_bonus_tokens, bonuses);
+    assert_eq!(presale.bonus_tokens_current_stage, 0);
+}
+
+#[test]
+fn advancing_an_expired_stage_keeps_the_schedule() {
+    // A day between the two stages
+    let mut presale = presale_state();
+    presale.stages[1].start_time = NOW + 100 + 86_400;
+    presale.stages[1].end_time = NOW + 200 + 86_400;
+
+    assert_eq!(presale.advance_stage(NOW + 99), Err(PresaleError::TooEarly.into()));
+    assert!(presale.advance_stage(NOW + 150).is_ok());
+    assert_eq!(presale.current_stage, 1);
+    assert_eq!(presale.stages[1].start_time, NOW + 100 + 86_400);
+    // The unsold first stage carries over
+    assert_eq!(presale.stage_tokens_remaining, 20_000 * TOKEN);
+
+    assert_eq!(
+        presale.fill_order(&mut user_info(), 50 * USD, 0, 10_000, NOW + 150),
+        Err(PresaleError::TooEarly.into())
+    );
+    assert!(presale.fill_order(&mut user_info(), 50 * USD, 0, 10_000, NOW + 100 + 86_400).is_ok());
+}
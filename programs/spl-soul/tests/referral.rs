@@ -0,0 +1,49 @@
+use anchor_lang::prelude::Pubkey;
+use spl_soul::presale::{ReferralConfig, ReferrerInfo};
+
+fn referrer() -> ReferrerInfo {
+    ReferrerInfo {
+        referrer: Pubkey::new_unique(),
+        referred_usd: 0,
+        bonus_tokens: 0,
+        bonus_tokens_claimed: 0,
+        commission_usd: 0,
+        commission_usdt: 0,
+        commission_usdc: 0,
+    }
+}
+
+#[test]
+fn splits_rewards_in_basis_points() {
+    let config = ReferralConfig {
+        bonus_bps: 500,
+        commission_bps: 300,
+        ..Default::default()
+    };
+    // 5% of 10,000 SOUL and 3% of $50
+    assert_eq!(
+        config.rewards(&referrer(), 10_000_000_000, 50_000_000),
+        Ok((500_000_000, 1_500_000))
+    );
+}
+
+#[test]
+fn caps_rewards_per_referrer() {
+    let config = ReferralConfig {
+        bonus_bps: 500,
+        commission_bps: 300,
+        max_bonus_tokens: 600_000_000,
+        max_commission_usd: 2_000_000,
+    };
+    let mut info = referrer();
+    info.bonus_tokens = 500_000_000;
+    info.commission_usd = 1_500_000;
+    assert_eq!(
+        config.rewards(&info, 10_000_000_000, 50_000_000),
+        Ok((100_000_000, 500_000))
+    );
+
+    info.bonus_tokens = 600_000_000;
+    info.commission_usd = 2_000_000;
+    assert_eq!(config.rewards(&info, 10_000_000_000, 50_000_000), Ok((0, 0)));
+}
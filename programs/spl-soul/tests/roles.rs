@@ -0,0 +1,62 @@
+use anchor_lang::prelude::Pubkey;
+use spl_soul::multisig::multisig_signer;
+use spl_soul::roles::*;
+
+#[test]
+fn grants_and_revokes_roles() {
+    let admin = Pubkey::new_unique();
+    let pauser = Pubkey::new_unique();
+    let mut roles = RoleRegistry { members: vec![] };
+    roles.grant(Role::Admin, admin).unwrap();
+    roles.grant(Role::Pauser, pauser).unwrap();
+
+    assert!(roles.has_role(Role::Pauser, &pauser));
+    assert!(!roles.has_role(Role::Treasurer, &pauser));
+    assert!(!roles.has_role(Role::Pauser, &admin));
+    assert_eq!(roles.grant(Role::Pauser, pauser), Err(RoleError::AlreadyGranted));
+
+    roles.revoke(Role::Pauser, &pauser).unwrap();
+    assert!(!roles.has_role(Role::Pauser, &pauser));
+    assert_eq!(roles.revoke(Role::Pauser, &pauser), Err(RoleError::NotGranted));
+}
+
+#[test]
+fn keeps_last_admin_and_caps_members() {
+    let admin = Pubkey::new_unique();
+    let mut roles = RoleRegistry { members: vec![] };
+    roles.grant(Role::Admin, admin).unwrap();
+    assert_eq!(roles.revoke(Role::Admin, &admin), Err(RoleError::LastAdmin));
+
+    let second_admin = Pubkey::new_unique();
+    roles.grant(Role::Admin, second_admin).unwrap();
+    roles.revoke(Role::Admin, &admin).unwrap();
+
+    while roles.members.len() < MAX_ROLE_MEMBERS {
+        roles.grant(Role::Operator, Pubkey::new_unique()).unwrap();
+    }
+    assert_eq!(roles.grant(Role::Operator, Pubkey::new_unique()), Err(RoleError::RegistryFull));
+}
+
+#[test]
+fn multisig_admin_grants_separate_keys() {
+    // As set up by initialize_roles
+    let mut roles = RoleRegistry { members: vec![] };
+    roles.grant(Role::Admin, multisig_signer()).unwrap();
+    let authority = Pubkey::new_unique();
+    assert!(roles.has_role(Role::Admin, &multisig_signer()));
+    assert!(!roles.has_role(Role::Admin, &authority));
+
+    // Each role goes to its own key through a multisig proposal
+    let operator = Pubkey::new_unique();
+    let treasurer = Pubkey::new_unique();
+    let pauser = Pubkey::new_unique();
+    roles.grant(Role::Operator, operator).unwrap();
+    roles.grant(Role::Treasurer, treasurer).unwrap();
+    roles.grant(Role::Pauser, pauser).unwrap();
+
+    assert!(roles.has_role(Role::Operator, &operator));
+    assert!(roles.has_role(Role::Treasurer, &treasurer));
+    assert!(!roles.has_role(Role::Treasurer, &operator));
+    assert!(!roles.has_role(Role::Treasurer, &multisig_signer()));
+    assert!(roles.has_role(Role::Pauser, &pauser));
+}
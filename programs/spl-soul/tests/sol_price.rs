@@ -0,0 +1,63 @@
+use anchor_lang::prelude::*;
+use pyth_sdk_solana::state::{AccountType, PriceAccount, PriceStatus, MAGIC, VERSION_2};
+use spl_soul::presale::{load_sol_usd_price, usd_to_lamports, PresaleError};
+
+const NOW: i64 = 1_700_000_000;
+
+/// Builds the raw data of a Pyth price account quoting SOL/USD.
+fn mock_price_account(price: i64, conf: u64, expo: i32, publish_time: i64) -> Vec<u8> {
+    let mut account = PriceAccount {
+        magic: MAGIC,
+        ver: VERSION_2,
+        atype: AccountType::Price as u32,
+        expo,
+        timestamp: publish_time,
+        ..Default::default()
+    };
+    account.agg.price = price;
+    account.agg.conf = conf;
+    account.agg.status = PriceStatus::Trading;
+    bytemuck::bytes_of(&account).to_vec()
+}
+
+fn load(data: &mut [u8], now: i64) -> Result<pyth_sdk_solana::Price> {
+    let key = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let mut lamports = 0;
+    let info = AccountInfo::new(&key, false, false, &mut lamports, data, &owner, false, 0);
+    load_sol_usd_price(&info, now)
+}
+
+#[test]
+fn converts_usd_to_lamports() {
+    // $150.00000000 per SOL, $0.015 confidence
+    let mut data = mock_price_account(15_000_000_000, 1_500_000, -8, NOW);
+    let price = load(&mut data, NOW).unwrap();
+
+    // $50 buys a third of a SOL, rounded up in favour of the treasury
+    assert_eq!(usd_to_lamports(50_000_000, &price).unwrap(), 333_333_334);
+    // $300 buys exactly two SOL
+    assert_eq!(usd_to_lamports(300_000_000, &price).unwrap(), 2_000_000_000);
+}
+
+#[test]
+fn rejects_stale_price() {
+    let mut data = mock_price_account(15_000_000_000, 1_500_000, -8, NOW - 61);
+    assert_eq!(load(&mut data, NOW).unwrap_err(), PresaleError::StalePrice.into());
+}
+
+#[test]
+fn rejects_low_confidence_price() {
+    // Confidence of 2% exceeds the 1% limit
+    let mut data = mock_price_account(15_000_000_000, 300_000_000, -8, NOW);
+    assert_eq!(
+        load(&mut data, NOW).unwrap_err(),
+        PresaleError::PriceConfidenceTooLow.into()
+    );
+}
+
+#[test]
+fn rejects_non_price_account() {
+    let mut data = vec![0u8; std::mem::size_of::<PriceAccount>()];
+    assert_eq!(load(&mut data, NOW).unwrap_err(), PresaleError::InvalidPriceFeed.into());
+}
@@ -0,0 +1,39 @@
+use spl_soul::supply::{AllocationSupply, SupplyAllocation, SupplyLedger};
+use spl_soul::TokenError;
+
+const CAP: u64 = 1_000_000;
+
+fn ledger() -> SupplyLedger {
+    SupplyLedger {
+        total_cap: CAP,
+        total_minted: 0,
+        vesting: AllocationSupply { cap: CAP / 2, minted: 0 },
+        dex_liquidity: AllocationSupply { cap: CAP / 4, minted: 0 },
+        cex_marketing: AllocationSupply { cap: CAP / 8, minted: 0 },
+        presale: AllocationSupply { cap: CAP / 8, minted: 0 },
+    }
+}
+
+#[test]
+fn existing_supply_counts_against_the_total_cap() {
+    let mut ledger = ledger();
+    ledger.record_existing_supply(CAP - 100).unwrap();
+    assert_eq!(ledger.total_minted, CAP - 100);
+
+    assert_eq!(
+        ledger.record_mint(SupplyAllocation::Vesting, 101),
+        Err(TokenError::SupplyCapExceeded.into())
+    );
+    ledger.record_mint(SupplyAllocation::Vesting, 100).unwrap();
+    assert_eq!(ledger.total_minted, CAP);
+}
+
+#[test]
+fn existing_supply_above_the_cap_is_rejected() {
+    let mut ledger = ledger();
+    assert_eq!(
+        ledger.record_existing_supply(CAP + 1),
+        Err(TokenError::SupplyCapExceeded.into())
+    );
+    assert_eq!(ledger.total_minted, 0);
+}
@@ -0,0 +1,74 @@
+use anchor_lang::prelude::Pubkey;
+use spl_soul::multisig::multisig_signer;
+use spl_soul::timelock::*;
+use spl_soul::TokenError;
+
+const DAY: i64 = 24 * 60 * 60;
+const NOW: i64 = 1_700_000_000;
+
+fn timelock() -> Timelock {
+    Timelock {
+        admin: multisig_signer(),
+        min_delay: 2 * DAY,
+        action_count: 0,
+    }
+}
+
+fn queued(eta: i64) -> QueuedAction {
+    QueuedAction {
+        index: 0,
+        eta,
+        program_id: Pubkey::new_unique(),
+        accounts: vec![],
+        data: vec![],
+        executed: false,
+    }
+}
+
+#[test]
+fn enforces_minimum_delay() {
+    let timelock = timelock();
+    assert_eq!(timelock.eta(NOW, 2 * DAY), Ok(NOW + 2 * DAY));
+    assert_eq!(timelock.eta(NOW, DAY), Err(TokenError::DelayTooShort.into()));
+    assert_eq!(timelock.eta(i64::MAX, 2 * DAY), Err(TokenError::MathOverflow.into()));
+}
+
+#[test]
+fn rejects_execution_before_eta() {
+    let eta = timelock().eta(NOW, 2 * DAY).unwrap();
+    let mut action = queued(eta);
+
+    // Warp the clock up to and past the eta
+    assert_eq!(action.check_executable(NOW), Err(TokenError::TooEarly.into()));
+    assert_eq!(action.check_executable(eta - 1), Err(TokenError::TooEarly.into()));
+    assert_eq!(action.check_executable(eta), Ok(()));
+
+    action.executed = true;
+    assert_eq!(action.check_executable(eta + DAY), Err(TokenError::ActionAlreadyExecuted.into()));
+}
+
+#[test]
+fn only_the_multisig_signer_administers_the_queue() {
+    let mut timelock = timelock();
+    assert_eq!(
+        timelock.configure(Pubkey::new_unique(), 2 * DAY),
+        Err(TokenError::InvalidTimelockAdmin.into())
+    );
+    assert_eq!(
+        timelock.configure(multisig_signer(), MIN_TIMELOCK_DELAY - 1),
+        Err(TokenError::DelayTooShort.into())
+    );
+    assert_eq!(timelock.configure(multisig_signer(), 3 * DAY), Ok(()));
+    assert_eq!(timelock.min_delay, 3 * DAY);
+}
+
+#[test]
+fn rejects_execution_after_grace_period() {
+    let eta = timelock().eta(NOW, 2 * DAY).unwrap();
+    let action = queued(eta);
+    assert_eq!(action.check_executable(eta + TIMELOCK_GRACE_PERIOD), Ok(()));
+    assert_eq!(
+        action.check_executable(eta + TIMELOCK_GRACE_PERIOD + 1),
+        Err(TokenError::ActionExpired.into())
+    );
+}
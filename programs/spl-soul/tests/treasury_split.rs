@@ -0,0 +1,105 @@
+use anchor_lang::prelude::Pubkey;
+use spl_soul::presale::*;
+
+const DAY: i64 = 24 * 60 * 60;
+const NOW: i64 = 1_700_000_000;
+
+fn shares(bps: &[u16]) -> Vec<SplitShare> {
+    bps.iter()
+        .map(|bps| SplitShare { wallet: Pubkey::new_unique(), bps: *bps })
+        .collect()
+}
+
+#[test]
+fn validates_split_table() {
+    let invalid = Some(PresaleError::InvalidTreasurySplit.into());
+    assert!(TreasurySplit::new(&shares(&[5_000, 3_000, 2_000])).is_ok());
+    assert!(TreasurySplit::new(&[]).is_ok());
+    assert_eq!(TreasurySplit::new(&shares(&[5_000, 3_000])).err(), invalid);
+    assert_eq!(TreasurySplit::new(&shares(&[2_000, 2_000, 2_000, 2_000, 1_000, 1_000])).err(), invalid);
+
+    let mut duplicate = shares(&[5_000, 5_000]);
+    duplicate[1].wallet = duplicate[0].wallet;
+    assert_eq!(TreasurySplit::new(&duplicate).err(), invalid);
+}
+
+#[test]
+fn splits_each_payment_by_currency() {
+    // Operations 50%, liquidity reserve 30%, marketing 20%
+    let mut split = TreasurySplit::new(&shares(&[5_000, 3_000, 2_000])).unwrap();
+    split.credit(PaymentCurrency::USDT, 50_000_000).unwrap();
+    split.credit(PaymentCurrency::SOL, 1_000_000_001).unwrap();
+
+    let owed_usdt: Vec<u64> = split.recipients.iter().map(|recipient| recipient.owed_usdt).collect();
+    assert_eq!(owed_usdt, vec![25_000_000, 15_000_000, 10_000_000]);
+
+    // The rounding remainder goes to the first recipient
+    let owed_lamports: Vec<u64> = split.recipients.iter().map(|recipient| recipient.owed_lamports).collect();
+    assert_eq!(owed_lamports, vec![500_000_001, 300_000_000, 200_000_000]);
+
+    assert_eq!(split.owed(PaymentCurrency::USDT), 50_000_000);
+    assert_eq!(split.owed(PaymentCurrency::SOL), 1_000_000_001);
+    assert_eq!(split.owed(PaymentCurrency::USDC), 0);
+}
+
+#[test]
+fn rounding_remainder_goes_to_recipient_zero() {
+    // Every share of 100 rounds down to 33, the missing unit goes to the first recipient
+    let mut split = TreasurySplit::new(&shares(&[3_334, 3_333, 3_333])).unwrap();
+    split.credit(PaymentCurrency::USDC, 100).unwrap();
+    assert_eq!(split.recipients[0].owed_usdc, 33 + 1);
+    assert_eq!(split.recipients[1].owed_usdc, 33);
+    assert_eq!(split.recipients[2].owed_usdc, 33);
+
+    // Even when it has the smallest share
+    let mut split = TreasurySplit::new(&shares(&[1_000, 4_500, 4_500])).unwrap();
+    split.credit(PaymentCurrency::USDT, 7).unwrap();
+    assert_eq!(split.recipients[0].owed_usdt, 1);
+    assert_eq!(split.recipients[1].owed_usdt, 3);
+    assert_eq!(split.recipients[2].owed_usdt, 3);
+    assert_eq!(split.owed(PaymentCurrency::USDT), 7);
+}
+
+fn policy(destinations: Vec<Pubkey>, period_limit: u64) -> TreasuryPolicy {
+    let allowance = WithdrawalAllowance { period_limit, ..Default::default() };
+    TreasuryPolicy { destinations, usdt: allowance, usdc: allowance, sol: allowance }
+}
+
+#[test]
+fn payouts_only_go_to_policy_destinations() {
+    let shares = shares(&[5_000, 5_000]);
+    let mut split = TreasurySplit::new(&shares).unwrap();
+    split.credit(PaymentCurrency::USDT, 1_000).unwrap();
+    let mut policy = policy(vec![shares[0].wallet], 10_000);
+
+    assert_eq!(
+        split.take_payout(1, &mut policy, true, true, NOW).err(),
+        Some(PresaleError::InvalidDestination.into())
+    );
+    assert_eq!(split.take_payout(0, &mut policy, true, true, NOW), Ok((500, 0, 0)));
+    assert_eq!(policy.usdt.withdrawn, 500);
+    assert_eq!(
+        split.take_payout(0, &mut policy, true, true, NOW),
+        Err(PresaleError::NothingToClaim.into())
+    );
+}
+
+#[test]
+fn payouts_are_charged_to_the_allowance() {
+    let shares = shares(&[10_000]);
+    let mut split = TreasurySplit::new(&shares).unwrap();
+    split.credit(PaymentCurrency::USDC, 1_500).unwrap();
+    split.credit(PaymentCurrency::SOL, 700).unwrap();
+    let mut policy = policy(vec![shares[0].wallet], 1_000);
+    policy.usdc.withdraw(400, NOW).unwrap();
+
+    // What is left of today's allowance is paid, the rest stays owed
+    assert_eq!(split.take_payout(0, &mut policy, false, true, NOW), Ok((0, 600, 700)));
+    assert_eq!(split.owed(PaymentCurrency::USDC), 900);
+    assert_eq!(
+        split.take_payout(0, &mut policy, false, true, NOW + 1),
+        Err(PresaleError::WithdrawalLimitExceeded.into())
+    );
+    assert_eq!(split.take_payout(0, &mut policy, false, true, NOW + DAY), Ok((0, 900, 0)));
+    assert_eq!(split.owed(PaymentCurrency::USDC), 0);
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use spl_soul::presale::PresaleError;
+use spl_soul::vesting::{ReleaseMode, VestingTerms};
+
+const DAY: i64 = 24 * 60 * 60;
+const TOTAL: u64 = 1_200_000_000;
+
+fn terms(tge_percentage: u8, cliff_days: i64, release: ReleaseMode) -> VestingTerms {
+    VestingTerms {
+        tge_percentage,
+        cliff_seconds: cliff_days * DAY,
+        period_seconds: 30 * DAY,
+        period_count: 4,
+        release,
+    }
+}
+
+#[test]
+fn releases_tge_share_at_start() {
+    let terms = terms(25, 0, ReleaseMode::Linear);
+    assert_eq!(terms.vested_amount(TOTAL, -1), 0);
+    assert_eq!(terms.vested_amount(TOTAL, 0), 300_000_000);
+
+    let all_at_tge = VestingTerms {
+        tge_percentage: 100,
+        period_count: 0,
+        ..terms
+    };
+    assert_eq!(all_at_tge.vested_amount(TOTAL, 0), TOTAL);
+}
+
+#[test]
+fn holds_back_until_cliff() {
+    let terms = terms(10, 90, ReleaseMode::Linear);
+    assert_eq!(terms.vested_amount(TOTAL, 90 * DAY - 1), 120_000_000);
+    assert_eq!(terms.vested_amount(TOTAL, 90 * DAY), 120_000_000);
+}
+
+#[test]
+fn releases_linearly_after_cliff() {
+    let terms = terms(10, 90, ReleaseMode::Linear);
+    // A quarter of the four periods is a quarter of the 90% after TGE
+    assert_eq!(terms.vested_amount(TOTAL, 120 * DAY), 120_000_000 + 270_000_000);
+    assert_eq!(terms.vested_amount(TOTAL, 135 * DAY), 120_000_000 + 405_000_000);
+    assert_eq!(terms.vested_amount(TOTAL, 210 * DAY), TOTAL);
+    assert_eq!(terms.vested_amount(TOTAL, 1_000 * DAY), TOTAL);
+}
+
+#[test]
+fn releases_periodic_tranches() {
+    let terms = terms(0, 90, ReleaseMode::Periodic);
+    assert_eq!(terms.vested_amount(TOTAL, 90 * DAY - 1), 0);
+    // First tranche at the cliff, then one at the start of every period
+    assert_eq!(terms.vested_amount(TOTAL, 90 * DAY), 300_000_000);
+    assert_eq!(terms.vested_amount(TOTAL, 120 * DAY - 1), 300_000_000);
+    assert_eq!(terms.vested_amount(TOTAL, 120 * DAY), 600_000_000);
+    assert_eq!(terms.vested_amount(TOTAL, 180 * DAY), TOTAL);
+    assert_eq!(terms.vested_amount(TOTAL, 1_000 * DAY), TOTAL);
+}
+
+#[test]
+fn repeated_claims_only_pay_the_difference() {
+    let terms = terms(25, 0, ReleaseMode::Linear);
+    let vesting_start = 1_700_000_000;
+    let mut user = common::user_info();
+    user.total_purchased = TOTAL;
+
+    // Nothing before the sale ends
+    assert_eq!(
+        user.claim_vested(&terms, vesting_start, vesting_start - 1),
+        Err(PresaleError::NothingToClaim.into())
+    );
+    assert_eq!(user.claim_vested(&terms, vesting_start, vesting_start), Ok(300_000_000));
+    assert_eq!(
+        user.claim_vested(&terms, vesting_start, vesting_start + 30 * DAY),
+        Ok(225_000_000)
+    );
+    assert_eq!(
+        user.claim_vested(&terms, vesting_start, vesting_start + 30 * DAY),
+        Err(PresaleError::NothingToClaim.into())
+    );
+    assert_eq!(
+        user.claim_vested(&terms, vesting_start, vesting_start + 200 * DAY),
+        Ok(675_000_000)
+    );
+    assert_eq!(user.tokens_claimed, TOTAL);
+    assert_eq!(user.last_claim_time, vesting_start + 200 * DAY);
+}
+
+#[test]
+fn presale_claims_start_with_a_periodic_tranche_at_the_cliff() {
+    let terms = terms(0, 90, ReleaseMode::Periodic);
+    let vesting_start = 1_700_000_000;
+    let mut user = common::user_info();
+    user.total_purchased = TOTAL;
+
+    assert_eq!(
+        user.claim_vested(&terms, vesting_start, vesting_start + 90 * DAY - 1),
+        Err(PresaleError::NothingToClaim.into())
+    );
+    assert_eq!(user.claim_vested(&terms, vesting_start, vesting_start + 90 * DAY), Ok(300_000_000));
+    assert_eq!(
+        user.claim_vested(&terms, vesting_start, vesting_start + 119 * DAY),
+        Err(PresaleError::NothingToClaim.into())
+    );
+    assert_eq!(user.claim_vested(&terms, vesting_start, vesting_start + 150 * DAY), Ok(600_000_000));
+}
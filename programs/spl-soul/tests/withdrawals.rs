@@ -0,0 +1,47 @@
+use anchor_lang::prelude::Pubkey;
+use spl_soul::presale::*;
+
+const DAY: i64 = 24 * 60 * 60;
+const NOW: i64 = 1_700_000_000;
+
+fn allowance(period_limit: u64) -> WithdrawalAllowance {
+    WithdrawalAllowance { period_limit, ..Default::default() }
+}
+
+#[test]
+fn caps_withdrawals_per_period() {
+    let mut usdt = allowance(100_000_000_000);
+    assert_eq!(usdt.withdraw(60_000_000_000, NOW), Ok(40_000_000_000));
+    assert_eq!(usdt.withdraw(40_000_000_000, NOW + 1), Ok(0));
+    assert_eq!(usdt.withdraw(1, NOW + DAY - 1), Err(PresaleError::WithdrawalLimitExceeded.into()));
+
+    // A rejected withdrawal uses none of the allowance
+    let mut usdc = allowance(100);
+    assert_eq!(usdc.withdraw(101, NOW), Err(PresaleError::WithdrawalLimitExceeded.into()));
+    assert_eq!(usdc.withdraw(100, NOW), Ok(0));
+}
+
+#[test]
+fn resets_allowance_after_a_period() {
+    let mut sol = allowance(1_000);
+    sol.withdraw(1_000, NOW).unwrap();
+    assert_eq!(sol.withdraw(400, NOW + DAY), Ok(600));
+    assert_eq!(sol.period_start, NOW + DAY);
+
+    // Nothing can leave before a limit is set
+    assert_eq!(allowance(0).withdraw(1, NOW), Err(PresaleError::WithdrawalLimitExceeded.into()));
+}
+
+#[test]
+fn only_allowlisted_destinations_receive_funds() {
+    let treasury = Pubkey::new_unique();
+    let mut policy = TreasuryPolicy {
+        destinations: vec![treasury],
+        usdt: allowance(1),
+        usdc: allowance(2),
+        sol: allowance(3),
+    };
+    assert!(policy.is_destination(&treasury));
+    assert!(!policy.is_destination(&Pubkey::new_unique()));
+    assert_eq!(policy.allowance(PaymentCurrency::USDC).period_limit, 2);
+}